@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+
+use crate::{barrier_shapes::{Shape, text}, lbm::SummaryStat};
+
+//Which annotations are drawn over the field, all off until turned on through WASMInteraction
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub struct AnnotationSettings{
+    pub colorbar: bool,
+    pub summary_stat: bool,
+    pub step_count: bool,
+    pub reynolds: bool,
+}
+
+impl AnnotationSettings{
+    pub fn any(&self) -> bool{
+        self.colorbar || self.summary_stat || self.step_count || self.reynolds
+    }
+}
+
+//What the annotations report. The statistic is None when only streamlines are shown, the range is None then
+//too and while an automatic range hasn't been read back yet
+pub struct AnnotationValues{
+    pub summary_stat: Option<SummaryStat>,
+    pub step_count: usize,
+    pub reynolds: f32,
+    pub range: Option<(f32, f32)>,
+}
+
+//Packed RGBA, red in the lowest byte as unpack4x8unorm expects
+const TEXT: u32 = 0xffffffff;
+const BACKDROP: u32 = 0x99000000;
+//Set on a cell index when its payload is a color map position rather than a color
+const COLOR_MAP_ENTRY: u32 = 0x80000000;
+
+//Cells of the annotation layer in window cells as (cell index, payload) pairs for annotation.wgsl. Text grows
+//with the lattice in whole multiples of the font's grid so strokes land exactly on cells
+pub fn build(settings: AnnotationSettings, values: &AnnotationValues, xdim: u32, ydim: u32) -> Vec<u32>{
+    let mut layer = Layer{ cells: BTreeMap::new(), xdim: xdim as isize, ydim: ydim as isize };
+    let size = 6.0 * (ydim as f64/240.0).round().max(1.0);
+    let margin = size;
+    let padding = (size/2.0).round();
+
+    let mut lines = Vec::<String>::new();
+    if settings.summary_stat{
+        lines.push(stat_name(values.summary_stat).to_owned());
+    }
+    if settings.step_count{
+        lines.push(format!("Step {}", values.step_count));
+    }
+    if settings.reynolds{
+        lines.push(format!("Re {:.0}", values.reynolds));
+    }
+    if !lines.is_empty(){
+        let lines = lines.join("\n");
+        let (width, height) = text::measure(&lines, size);
+        layer.rectangle((margin - padding, margin - padding), (width + 2.0 * padding, height + 2.0 * padding), BACKDROP, false);
+        layer.text(&lines, size, (margin, margin));
+    }
+
+    if let (true, Some((low, high))) = (settings.colorbar, values.range){
+        colorbar(&mut layer, (low, high), size, margin, padding);
+    }
+
+    layer.cells.iter().flat_map(|(index, (color_map, payload))| [index | if *color_map { COLOR_MAP_ENTRY } else { 0 }, *payload]).collect()
+}
+
+//Bar through the whole color map in the bottom right corner with labelled ticks underneath. Uses as many ticks
+//as fit, from five down to just the ends
+fn colorbar(layer: &mut Layer, (low, high): (f32, f32), size: f64, margin: f64, padding: f64){
+    let bar_width = (layer.xdim as f64/3.0).round().clamp(20.0, (layer.xdim as f64 - 2.0 * margin).max(1.0));
+    let bar_height = (1.5 * size).round();
+    let tick_height = (size/2.0).round();
+    let height = bar_height + tick_height + padding + size;
+    let left = layer.xdim as f64 - margin - bar_width;
+    let top = layer.ydim as f64 - margin - height;
+
+    let ticks = [5, 3, 2].into_iter()
+        .map(|count| (0..count).map(|i| i as f32/(count - 1) as f32).map(|t| (t, format_tick(low + t * (high - low)))).collect::<Vec<_>>())
+        .find(|ticks| {
+            let widest = ticks.iter().map(|(_, label)| text::measure(label, size).0).fold(0.0, f64::max);
+            widest + size <= bar_width/(ticks.len() - 1) as f64
+        })
+        .unwrap_or_else(|| [0.0, 1.0].map(|t| (t, format_tick(low + t * (high - low)))).to_vec());
+
+    layer.rectangle((left - padding, top - padding), (bar_width + 2.0 * padding, height + 2.0 * padding), BACKDROP, false);
+    for column in 0..bar_width as isize{
+        let position = (column as f64/(bar_width - 1.0).max(1.0) * 65535.0).round() as u32;
+        layer.rectangle((left + column as f64, top), (1.0, bar_height), position, true);
+    }
+    for (t, label) in ticks.iter(){
+        let x = (left + *t as f64 * (bar_width - 1.0)).round();
+        layer.rectangle((x, top + bar_height), (1.0, tick_height), TEXT, false);
+        //End labels line up with the ends of the bar, the rest are centered on their tick
+        let width = text::measure(label, size).0;
+        let label_left = if *t == 0.0 { left } else if *t == 1.0 { left + bar_width - width } else { x - width/2.0 };
+        layer.text(label, size, (label_left.round(), top + bar_height + tick_height + padding));
+    }
+}
+
+//Three significant figures, with an exponent for very large or small values
+fn format_tick(value: f32) -> String{
+    let magnitude = value.abs();
+    if magnitude == 0.0{
+        "0".to_owned()
+    } else if !(1e-2..1e4).contains(&magnitude){
+        format!("{:.1e}", value)
+    } else {
+        let decimals = (2 - magnitude.log10().floor() as i32).max(0) as usize;
+        format!("{:.*}", decimals, value)
+    }
+}
+
+fn stat_name(summary_stat: Option<SummaryStat>) -> &'static str{
+    match summary_stat {
+        Some(SummaryStat::Curl) => "Curl",
+        Some(SummaryStat::Ux) => "Ux",
+        Some(SummaryStat::Uy) => "Uy",
+        Some(SummaryStat::Rho) => "Density",
+        Some(SummaryStat::Speed) => "Speed",
+        None => "Streamlines",
+    }
+}
+
+//Payloads by cell index, later writes cover earlier ones
+struct Layer{
+    cells: BTreeMap<u32, (bool, u32)>,
+    xdim: isize,
+    ydim: isize,
+}
+
+impl Layer{
+
+    fn rectangle(&mut self, corner: (f64, f64), size: (f64, f64), payload: u32, color_map: bool){
+        let (left, top) = (corner.0.round() as isize, corner.1.round() as isize);
+        let (right, bottom) = ((corner.0 + size.0).round() as isize, (corner.1 + size.1).round() as isize);
+        for y in top.max(0)..bottom.min(self.ydim){
+            for x in left.max(0)..right.min(self.xdim){
+                self.cells.insert((x + y * self.xdim) as u32, (color_map, payload));
+            }
+        }
+    }
+
+    fn text(&mut self, text: &str, size: f64, corner: (f64, f64)){
+        for p in text::render(text, size, corner, 0.0, 1.0, self.xdim, self.ydim).get_mask().points(){
+            self.cells.insert((p.0 + p.1 * self.xdim) as u32, (false, TEXT));
+        }
+    }
+}
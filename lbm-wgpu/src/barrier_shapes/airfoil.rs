@@ -0,0 +1,155 @@
+use std::f64::consts::PI;
+
+use super::{Shape, raster, mask::Mask};
+
+//Mean line constants for the standard and reflexed NACA 5-digit series, indexed by the P digit (design cl = 0.3)
+const FIVE_DIGIT_STANDARD: [(f64, f64); 5] = [(0.0580, 361.4), (0.1260, 51.64), (0.2025, 15.957), (0.2900, 6.643), (0.3910, 3.230)];
+#[allow(clippy::approx_constant)]
+const FIVE_DIGIT_REFLEX: [(f64, f64, f64); 4] = [(0.1300, 51.99, 0.000764), (0.2170, 15.793, 0.00677), (0.3180, 6.520, 0.0303), (0.4410, 3.191, 0.1355)];
+
+type Point = (f64, f64);
+
+pub struct Airfoil{
+    pub mask: Mask
+}
+
+impl Shape for Airfoil{
+    fn get_mask(&self) -> &Mask {
+        &self.mask
+    }
+}
+
+impl Airfoil{
+
+    //Generates a NACA 4 or 5 digit section, leading_edge is in lattice cells and angle_of_attack in degrees
+    pub fn naca(
+        code: &str,
+        chord: f64,
+        leading_edge: (f64, f64),
+        angle_of_attack: f64,
+        xdim: isize,
+        ydim: isize) -> Result<Airfoil, String>{
+
+            let digits: Vec<u32> = code.trim().chars().map(|c| c.to_digit(10)).collect::<Option<Vec<u32>>>()
+                .ok_or_else(|| format!("NACA code {} must only contain digits", code))?;
+
+            let samples = (chord.ceil() as usize).clamp(40, 2000);
+            let outline = match digits.len() {
+                4 => {
+                    let camber = digits[0] as f64 / 100.0;
+                    let position = digits[1] as f64 / 10.0;
+                    let thickness = (digits[2] * 10 + digits[3]) as f64 / 100.0;
+                    Self::outline(samples, thickness, |x| Self::four_digit_camber(camber, position, x))
+                },
+                5 => {
+                    let lift = digits[0] as f64 * 0.15;
+                    let thickness = (digits[3] * 10 + digits[4]) as f64 / 100.0;
+                    let (position, reflex) = (digits[1] as usize, digits[2]);
+                    match (reflex, position) {
+                        (0, 1..=5) => {
+                            let (r, k1) = FIVE_DIGIT_STANDARD[position - 1];
+                            Self::outline(samples, thickness, |x| Self::five_digit_camber(r, k1 * lift / 0.3, x))
+                        },
+                        (1, 2..=5) => {
+                            let (r, k1, k2_k1) = FIVE_DIGIT_REFLEX[position - 2];
+                            Self::outline(samples, thickness, |x| Self::reflex_camber(r, k1 * lift / 0.3, k2_k1, x))
+                        },
+                        _ => return Err(format!("NACA {} is not a tabulated 5-digit mean line", code)),
+                    }
+                },
+                _ => return Err(format!("NACA code {} must have 4 or 5 digits", code)),
+            };
+
+            Ok(Self::rasterize(&outline, chord, leading_edge, angle_of_attack, xdim, ydim))
+    }
+
+    //Loads a Selig format coordinate file, the header line and anything that isn't a coordinate pair is skipped
+    pub fn from_selig(
+        source: &str,
+        chord: f64,
+        leading_edge: (f64, f64),
+        angle_of_attack: f64,
+        xdim: isize,
+        ydim: isize) -> Result<Airfoil, String>{
+
+            let coordinates: Vec<Point> = source.lines()
+                .filter_map(|line| {
+                    let values: Vec<f64> = line.split_whitespace().filter_map(|v| v.parse::<f64>().ok()).collect();
+                    if values.len() == 2 { Some((values[0], values[1])) } else { None }
+                })
+                .collect();
+
+            if coordinates.len() < 3{
+                return Err("Airfoil file has fewer than 3 coordinates".to_string());
+            }
+
+            //Normalize so the section spans x = 0 to 1
+            let min_x = coordinates.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+            let max_x = coordinates.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+            if max_x <= min_x{
+                return Err("Airfoil file has no chord length".to_string());
+            }
+            let outline: Vec<Point> = coordinates.iter().map(|p| ((p.0 - min_x)/(max_x - min_x), p.1/(max_x - min_x))).collect();
+
+            Ok(Self::rasterize(&outline, chord, leading_edge, angle_of_attack, xdim, ydim))
+    }
+
+    //Closed outline from the trailing edge over the upper surface and back along the lower surface
+    fn outline(samples: usize, thickness: f64, camber: impl Fn(f64) -> (f64, f64)) -> Vec<Point>{
+        let stations: Vec<f64> = (0..=samples).map(|i| (1.0 - (PI * i as f64 / samples as f64).cos())/2.0).collect();
+        let surface = |x: f64, side: f64| {
+            //Closed trailing edge thickness distribution
+            let half = 5.0 * thickness * (0.2969 * x.sqrt() - 0.1260 * x - 0.3516 * x.powi(2) + 0.2843 * x.powi(3) - 0.1036 * x.powi(4));
+            let (yc, slope) = camber(x);
+            let theta = slope.atan();
+            (x - side * half * theta.sin(), yc + side * half * theta.cos())
+        };
+        stations.iter().rev().map(|x| surface(*x, 1.0))
+            .chain(stations.iter().skip(1).map(|x| surface(*x, -1.0)))
+            .collect()
+    }
+
+    fn four_digit_camber(m: f64, p: f64, x: f64) -> (f64, f64){
+        if m == 0.0 || p == 0.0{
+            return (0.0, 0.0);
+        }
+        if x < p{
+            (m/(p * p) * (2.0 * p * x - x * x), 2.0 * m/(p * p) * (p - x))
+        } else {
+            (m/((1.0 - p) * (1.0 - p)) * (1.0 - 2.0 * p + 2.0 * p * x - x * x), 2.0 * m/((1.0 - p) * (1.0 - p)) * (p - x))
+        }
+    }
+
+    fn five_digit_camber(r: f64, k1: f64, x: f64) -> (f64, f64){
+        if x < r{
+            (k1/6.0 * (x.powi(3) - 3.0 * r * x * x + r * r * (3.0 - r) * x), k1/6.0 * (3.0 * x * x - 6.0 * r * x + r * r * (3.0 - r)))
+        } else {
+            (k1 * r.powi(3)/6.0 * (1.0 - x), -k1 * r.powi(3)/6.0)
+        }
+    }
+
+    fn reflex_camber(r: f64, k1: f64, k2_k1: f64, x: f64) -> (f64, f64){
+        let tail = k2_k1 * (1.0 - r).powi(3);
+        if x < r{
+            (k1/6.0 * ((x - r).powi(3) - tail * x - r.powi(3) * x + r.powi(3)), k1/6.0 * (3.0 * (x - r).powi(2) - tail - r.powi(3)))
+        } else {
+            (k1/6.0 * (k2_k1 * (x - r).powi(3) - tail * x - r.powi(3) * x + r.powi(3)), k1/6.0 * (3.0 * k2_k1 * (x - r).powi(2) - tail - r.powi(3)))
+        }
+    }
+
+    //Scales the unit chord outline, pitches the nose up by angle_of_attack about the leading edge and fills it
+    fn rasterize(outline: &[Point], chord: f64, leading_edge: (f64, f64), angle_of_attack: f64, xdim: isize, ydim: isize) -> Airfoil{
+        let (sin, cos) = angle_of_attack.to_radians().sin_cos();
+        //Lattice rows grow downwards so the airfoil's y axis is flipped
+        let lattice: Vec<Point> = outline.iter()
+            .map(|p| (leading_edge.0 + chord * (p.0 * cos + p.1 * sin), leading_edge.1 - chord * (p.1 * cos - p.0 * sin)))
+            .collect();
+
+        let mut mask = Mask::new_empty();
+        raster::fill(std::slice::from_ref(&lattice), true, xdim, ydim, &mut mask);
+        raster::stroke(&lattice, true, true, xdim, ydim, &mut mask);
+        Airfoil{
+            mask: mask.clip(xdim, ydim)
+        }
+    }
+}
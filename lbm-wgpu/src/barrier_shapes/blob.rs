@@ -1,48 +1,37 @@
-use std::collections::HashSet;
+use super::{Shape, mask::Mask};
 
-
-use super::Shape;
 pub struct Blob{
-    pub points: HashSet<(isize, isize, bool)>
+    pub mask: Mask
 }
 
 impl Shape for Blob{
-    fn get_points(&self) -> &HashSet<(isize, isize, bool)> {
-        &self.points
-    }
-    fn is_empty(&self) -> bool {
-        self.points.is_empty()
+    fn get_mask(&self) -> &Mask {
+        &self.mask
     }
 }
 
 impl Blob{
-    pub fn new(points: HashSet<(isize, isize, bool)>) -> Blob{
+    pub fn new(mask: Mask) -> Blob{
         Blob{
-            points
+            mask
         }
     }
 
     pub fn new_empty() -> Blob{
-        Blob { points: HashSet::<(isize, isize, bool)>::new() }
+        Blob { mask: Mask::new_empty() }
     }
 
-    pub fn add(&mut self, points: &Vec<(isize, isize, bool)>, xdim: u32, ydim: u32){
-        for p in points{
-            if (p.0 as u32) < xdim && (p.1 as u32) < ydim{
-                self.points.remove(&(p.0, p.1, !p.2));
-                self.points.insert(p.clone());
-            }
-        }
+    //Draws or erases every cell the shape touches, later shapes win where they overlap
+    pub fn join(&mut self, shape: &dyn Shape){
+        self.mask.join_mask(shape.get_mask());
     }
 
-    pub fn join(&mut self, shape: &dyn Shape){
-        for p in shape.get_points(){
-            self.points.remove(&(p.0, p.1, !p.2));
-            self.points.insert(p.clone());
-        }
+    //Erases every cell the shape touches, whether it draws or erases them
+    pub fn erase(&mut self, shape: &dyn Shape){
+        self.mask.erase(shape.get_mask());
     }
 
     pub fn empty(&mut self){
-        self.points.clear();
+        self.mask.empty();
     }
-}
\ No newline at end of file
+}
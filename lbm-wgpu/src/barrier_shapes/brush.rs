@@ -0,0 +1,68 @@
+use wasm_bindgen::prelude::*;
+
+use super::{raster, mask::Mask, primitive::Primitive};
+
+#[wasm_bindgen]
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum BrushShape{
+    Round,
+    Square,
+}
+
+//Pen used by the draw and erase tools, width is in lattice cells
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Brush{
+    pub width: f64,
+    pub shape: BrushShape,
+}
+
+//The draw tool's pen until one is configured
+impl Default for Brush{
+    fn default() -> Self{
+        Brush::new(3.0, BrushShape::Round)
+    }
+}
+
+impl Brush{
+
+    pub fn new(width: f64, shape: BrushShape) -> Brush{
+        Brush{
+            width: width.max(1.0),
+            shape,
+        }
+    }
+
+    //Stamping costs the width squared per cell of the stroke, and nothing wider than half the lattice is useful
+    pub fn fit(&self, xdim: isize, ydim: isize) -> Brush{
+        Brush::new(self.width.min((xdim.min(ydim)/2) as f64), self.shape)
+    }
+
+    //Cells covered by the brush when centered on a cell
+    pub fn offsets(&self) -> Vec<(isize, isize)>{
+        match self.shape {
+            BrushShape::Round => raster::pen(self.width),
+            BrushShape::Square => {
+                let size = self.width.round().max(1.0) as isize;
+                let low = -(size - 1)/2;
+                (low..low + size).flat_map(|y| (low..low + size).map(move |x| (x, y))).collect()
+            }
+        }
+    }
+
+    //Stamps the brush along every cell of the segment, so the ends of each segment get round (or square) caps
+    //and consecutive segments always overlap at their shared point
+    pub fn stroke(&self, start: (isize, isize), end: (isize, isize), value: bool, xdim: isize, ydim: isize, mask: &mut Mask){
+        let path = [(start.0 as f64, start.1 as f64), (end.0 as f64, end.1 as f64)];
+        raster::stamped_stroke(&path, false, &self.offsets(), value, xdim, ydim, mask);
+    }
+
+    //A round brush's stroke as a capsule the GPU can rasterize, as wide as the pen raster::pen stamps
+    pub fn segment(&self, start: (isize, isize), end: (isize, isize)) -> Primitive{
+        let radius = (self.width - 1.0).max(0.0)/2.0;
+        Primitive::Segment{
+            start: (start.0 as f64, start.1 as f64),
+            end: (end.0 as f64, end.1 as f64),
+            width: 2.0 * (radius * radius + 0.25).sqrt(),
+        }
+    }
+}
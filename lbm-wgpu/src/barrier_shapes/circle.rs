@@ -0,0 +1,32 @@
+use super::{Shape, mask::Mask};
+
+pub struct Circle{
+    pub mask: Mask
+}
+
+impl Shape for Circle{
+    fn get_mask(&self) -> &Mask {
+        &self.mask
+    }
+}
+
+impl Circle{
+    pub fn new(center: (isize, isize), radius: f64, xdim: isize, ydim: isize) -> Circle{
+        let extent = radius.ceil() as isize;
+        let min = ((center.0 - extent).max(0), (center.1 - extent).max(0));
+        let max = ((center.0 + extent).min(xdim - 1), (center.1 + extent).min(ydim - 1));
+        let mut mask = Mask::new_empty();
+        mask.reserve(min, max);
+        for y in min.1..=max.1{
+            for x in min.0..=max.0{
+                let (dx, dy) = ((x - center.0) as f64, (y - center.1) as f64);
+                if dx * dx + dy * dy <= radius * radius{
+                    mask.set((x, y), true);
+                }
+            }
+        }
+        Circle{
+            mask
+        }
+    }
+}
@@ -1,58 +1,223 @@
-use std::collections::HashSet;
-use super::{Shape, line::Line};
+use std::mem;
+use wasm_bindgen::prelude::*;
+
+use super::{Shape, line::Line, brush::{Brush, BrushShape}, transform::Symmetry, mask::Mask, primitive::Primitive};
+
+#[wasm_bindgen]
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum StrokeSmoothing{
+    Off,
+    CatmullRom,
+    Bezier,
+}
 
 pub struct Curve{
-    pub points: HashSet<(isize, isize, bool)>,
+    pub mask: Mask,
     pub last_point: Option<(isize, isize)>,
+    smoothing: StrokeSmoothing,
+    strength: f64,
+    erasing: bool,
+    brush: Brush,
+    symmetry: Symmetry,
+    //Latest pointer positions, smoothed strokes are drawn one segment behind the pointer since each
+    //segment needs the point after it to know its tangent
+    control: Vec<(isize, isize)>,
+    //Round brush segments with the value they write, drawn on the GPU while the mask keeps their cells
+    primitives: Vec<(Primitive, bool)>,
 }
 
 impl Shape for Curve{
-    fn get_points(&self) -> &HashSet<(isize, isize, bool)>{
-        &self.points
-    }
-
-    fn is_empty(&self) -> bool {
-        self.points.is_empty()
+    fn get_mask(&self) -> &Mask{
+        &self.mask
     }
 }
 
 impl Curve{
 
     pub fn new() -> Curve{
-        Curve { 
-            points: HashSet::<(isize, isize, bool)>::new(), 
+        Curve {
+            mask: Mask::new_empty(),
             last_point: None,
+            smoothing: StrokeSmoothing::Off,
+            strength: 1.0,
+            erasing: false,
+            brush: Brush::default(),
+            symmetry: Symmetry::Off,
+            control: Vec::<(isize, isize)>::new(),
+            primitives: Vec::<(Primitive, bool)>::new(),
         }
     }
 
-    pub fn add_segment(&mut self, next: (isize, isize), xdim: isize, ydim: isize){
-        if self.last_point != None{
-            self.points.extend(&Line::new(self.last_point.unwrap().clone(), next, xdim, ydim).unwrap().points);
-            self.last_point = Some(next);
+    //Strength runs from 0 (straight segments) to 1 (full spline)
+    pub fn set_smoothing(&mut self, smoothing: StrokeSmoothing, strength: f64){
+        self.smoothing = smoothing;
+        self.strength = strength.clamp(0.0, 1.0);
+    }
+
+    //Curves draw and erase with the default brush until one is set
+    pub fn set_brush(&mut self, brush: Brush){
+        self.brush = brush;
+    }
+
+    //Every cell added afterwards is also added at its mirror images
+    pub fn set_symmetry(&mut self, symmetry: Symmetry){
+        self.symmetry = symmetry;
+    }
+
+    //Empty curve that picks up a smoothed stroke where this one left off
+    pub fn continuation(&self) -> Curve{
+        let mut curve = Curve::new();
+        curve.set_smoothing(self.smoothing, self.strength);
+        curve.brush = self.brush;
+        curve.symmetry = self.symmetry;
+        if self.smoothing != StrokeSmoothing::Off{
+            curve.erasing = self.erasing;
+            curve.control = self.control.clone();
+            curve.last_point = self.last_point;
         }
-        else {
-            self.points.insert((next.0, next.1, true));
-            self.last_point = Some(next);
+        curve
+    }
+
+    pub fn add_segment(&mut self, next: (isize, isize), xdim: isize, ydim: isize){
+        self.erasing = false;
+        if self.smoothing != StrokeSmoothing::Off{
+            return self.smooth_segment(next, xdim, ydim);
         }
+        self.brush_segment(next, xdim, ydim);
     }
 
     pub fn erase_segment(&mut self, next: (isize, isize), xdim: isize, ydim: isize){
-        if self.last_point != None{
-            self.points.extend(&Line::new_erased(self.last_point.unwrap().clone(), next, xdim, ydim).unwrap().points);
-            self.last_point = Some(next);
+        self.erasing = true;
+        if self.smoothing != StrokeSmoothing::Off{
+            return self.smooth_segment(next, xdim, ydim);
         }
-        else {
-            self.points.insert((next.0, next.1, false));
-            self.last_point = Some(next);
+        self.brush_segment(next, xdim, ydim);
+    }
+
+    //Draws the segment still trailing the pointer, called when the stroke ends
+    pub fn finish(&mut self, xdim: isize, ydim: isize){
+        let n = self.control.len();
+        if self.smoothing != StrokeSmoothing::Off && n >= 2{
+            let previous = if n >= 3 { self.control[n - 3] } else { self.control[n - 2] };
+            self.spline(previous, self.control[n - 2], self.control[n - 1], self.control[n - 1], xdim, ydim);
         }
+        self.control.clear();
     }
 
     pub fn empty(&mut self){
-        self.points.clear();
+        self.mask.empty();
         self.last_point = None;
+        self.control.clear();
+        self.primitives.clear();
     }
 
     pub fn join(&mut self, shape: Box<dyn Shape>){
-        self.points.extend(shape.get_points())
+        self.mask.join(&*shape)
+    }
+
+    //Segments not yet handed out, the mask still holds their cells
+    pub fn take_primitives(&mut self) -> Vec<(Primitive, bool)>{
+        mem::take(&mut self.primitives)
+    }
+
+    fn brush_segment(&mut self, next: (isize, isize), xdim: isize, ydim: isize){
+        let start = self.last_point.unwrap_or(next);
+        self.line(start, next, xdim, ydim);
+        self.last_point = Some(next);
+    }
+
+    fn line(&mut self, start: (isize, isize), end: (isize, isize), xdim: isize, ydim: isize){
+        if self.brush.shape == BrushShape::Round{
+            let value = !self.erasing;
+            let ends = self.symmetry.images(end, xdim, ydim);
+            for (s, e) in self.symmetry.images(start, xdim, ydim).into_iter().zip(ends){
+                let segment = self.brush.segment(s, e);
+                self.mask.join_mask(&segment.rasterize(value, xdim as u32, ydim as u32));
+                self.primitives.push((segment, value));
+            }
+            return;
+        }
+        let line = Line::with_brush(start, end, &self.brush, !self.erasing, xdim, ydim);
+        self.insert(&line.mask, xdim, ydim);
+    }
+
+    fn insert(&mut self, mask: &Mask, xdim: isize, ydim: isize){
+        if self.symmetry == Symmetry::Off || mask.is_empty(){
+            return self.mask.join_mask(mask);
+        }
+        let ((x, y), width, height) = mask.rectangle();
+        for corner in [(x, y), (x + width as isize - 1, y + height as isize - 1)]{
+            for c in self.symmetry.images(corner, xdim, ydim){
+                self.mask.reserve(c, c);
+            }
+        }
+        for p in mask.points(){
+            for c in self.symmetry.images((p.0, p.1), xdim, ydim){
+                self.mask.set(c, p.2);
+            }
+        }
+    }
+
+    fn smooth_segment(&mut self, next: (isize, isize), xdim: isize, ydim: isize){
+        if self.control.last() == Some(&next){
+            return;
+        }
+        if self.last_point.is_none(){
+            self.line(next, next, xdim, ydim);
+        }
+        self.control.push(next);
+        self.last_point = Some(next);
+
+        let n = self.control.len();
+        if n >= 3{
+            let previous = if n >= 4 { self.control[n - 4] } else { self.control[n - 3] };
+            self.spline(previous, self.control[n - 3], self.control[n - 2], self.control[n - 1], xdim, ydim);
+        }
+        if n > 3{
+            self.control.remove(0);
+        }
+    }
+
+    //Rasterizes the stroke piece belonging to p1 and p2, p0 and p3 only shape the tangents
+    fn spline(&mut self, p0: (isize, isize), p1: (isize, isize), p2: (isize, isize), p3: (isize, isize), xdim: isize, ydim: isize){
+        let [p0, p1, p2, p3] = [p0, p1, p2, p3].map(|p| (p.0 as f64, p.1 as f64));
+        let s = self.strength;
+        let towards = |from: (f64, f64), to: (f64, f64), amount: f64| (from.0 + amount * (to.0 - from.0), from.1 + amount * (to.1 - from.1));
+        match self.smoothing {
+            //Catmull-Rom from p1 to p2 written as the equivalent cubic Bézier
+            StrokeSmoothing::CatmullRom => {
+                let c1 = (p1.0 + s * (p2.0 - p0.0)/6.0, p1.1 + s * (p2.1 - p0.1)/6.0);
+                let c2 = (p2.0 - s * (p3.0 - p1.0)/6.0, p2.1 - s * (p3.1 - p1.1)/6.0);
+                self.bezier([p1, c1, c2, p2], xdim, ydim);
+            },
+            //Runs between the midpoints either side of p1 using p1 as the control point, so corners get rounded off
+            _ => {
+                let (from, to) = (towards(p0, p1, 0.5), towards(p1, p2, 0.5));
+                self.bezier([from, towards(from, p1, s * 2.0/3.0), towards(to, p1, s * 2.0/3.0), to], xdim, ydim);
+                if p2 == p3{
+                    self.bezier([to, to, p2, p2], xdim, ydim);
+                }
+            },
+        }
     }
-}
\ No newline at end of file
+
+    fn bezier(&mut self, control: [(f64, f64); 4], xdim: isize, ydim: isize){
+        let distance = |a: (f64, f64), b: (f64, f64)| ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt();
+        let length = distance(control[0], control[1]) + distance(control[1], control[2]) + distance(control[2], control[3]);
+        let samples = (length/2.0).ceil().max(1.0) as usize;
+        let point = |t: f64| {
+            let u = 1.0 - t;
+            let weights = [u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t];
+            let x: f64 = control.iter().zip(weights).map(|(c, w)| c.0 * w).sum();
+            let y: f64 = control.iter().zip(weights).map(|(c, w)| c.1 * w).sum();
+            ((x.round() as isize).clamp(0, xdim - 1), (y.round() as isize).clamp(0, ydim - 1))
+        };
+
+        let mut previous = point(0.0);
+        for i in 1..=samples{
+            let next = point(i as f64/samples as f64);
+            self.line(previous, next, xdim, ydim);
+            previous = next;
+        }
+    }
+}
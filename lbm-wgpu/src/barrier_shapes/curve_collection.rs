@@ -1,29 +1,23 @@
-use std::collections::HashSet;
-use super::{Shape, curve::Curve};
+use super::{Shape, curve::Curve, mask::Mask};
 
 pub struct CurveCollection{
     last_point: Option<(isize, isize)>,
-    points: HashSet::<(isize, isize, bool)>
+    mask: Mask
 }
 
 impl Shape for CurveCollection{
-    fn get_points(&self) -> &HashSet<(isize, isize, bool)> {
-        &self.points
+    fn get_mask(&self) -> &Mask {
+        &self.mask
     }
-
-    fn is_empty(&self) -> bool {
-        self.points.is_empty()
-    }
-
 }
 
 impl CurveCollection{
     pub fn new() ->  CurveCollection{
-        CurveCollection {last_point: None, points: HashSet::<(isize, isize, bool)>::new() }
+        CurveCollection {last_point: None, mask: Mask::new_empty() }
     }
 
     pub fn add_curve(&mut self, curve: Curve){
         self.last_point = curve.last_point;
-        self.points.extend(curve.points);
+        self.mask.join_mask(&curve.mask);
     }
 }
@@ -0,0 +1,45 @@
+use super::{Shape, mask::Mask};
+
+pub struct Ellipse{
+    pub mask: Mask
+}
+
+impl Shape for Ellipse{
+    fn get_mask(&self) -> &Mask {
+        &self.mask
+    }
+}
+
+impl Ellipse{
+    //Rotation is in degrees, clockwise on screen
+    pub fn new(center: (f64, f64), radii: (f64, f64), rotation: f64, xdim: isize, ydim: isize) -> Ellipse{
+        let (sin, cos) = rotation.to_radians().sin_cos();
+        let (rx, ry) = (radii.0.max(0.5), radii.1.max(0.5));
+        let extent = rx.max(ry).ceil() as isize;
+        let (cx, cy) = (center.0.round() as isize, center.1.round() as isize);
+
+        let min = ((cx - extent).max(0), (cy - extent).max(0));
+        let max = ((cx + extent).min(xdim - 1), (cy + extent).min(ydim - 1));
+        let mut mask = Mask::new_empty();
+        mask.reserve(min, max);
+        for y in min.1..=max.1{
+            for x in min.0..=max.0{
+                let (dx, dy) = (x as f64 - center.0, y as f64 - center.1);
+                let (u, v) = (dx * cos + dy * sin, dy * cos - dx * sin);
+                if (u * u)/(rx * rx) + (v * v)/(ry * ry) <= 1.0{
+                    mask.set((x, y), true);
+                }
+            }
+        }
+        Ellipse{
+            mask
+        }
+    }
+
+    //Ellipse inscribed in the axis aligned box spanned by two corners
+    pub fn from_corners(corner_1: (isize, isize), corner_2: (isize, isize), xdim: isize, ydim: isize) -> Ellipse{
+        let center = ((corner_1.0 + corner_2.0) as f64/2.0, (corner_1.1 + corner_2.1) as f64/2.0);
+        let radii = ((corner_1.0 - corner_2.0).abs() as f64/2.0, (corner_1.1 - corner_2.1).abs() as f64/2.0);
+        Self::new(center, radii, 0.0, xdim, ydim)
+    }
+}
@@ -0,0 +1,32 @@
+use std::collections::VecDeque;
+
+use super::{blob::Blob, mask::Mask};
+
+//Fills the fluid region 4-connected to seed, refusing once it grows past limit cells so a click outside
+//any enclosed outline doesn't turn the whole channel solid. solid takes a cell index (x + y * xdim)
+pub fn fill(solid: impl Fn(usize) -> bool, seed: (isize, isize), limit: usize, xdim: isize, ydim: isize) -> Result<Blob, String>{
+    let index = |p: (isize, isize)| (p.0 + p.1 * xdim) as usize;
+    let in_bounds = |p: (isize, isize)| p.0 >= 0 && p.1 >= 0 && p.0 < xdim && p.1 < ydim;
+    if !in_bounds(seed) || solid(index(seed)){
+        return Err("Fill must start on a fluid cell".to_string());
+    }
+
+    let mut points = Vec::<(isize, isize, bool)>::new();
+    let mut visited = vec![false; (xdim * ydim) as usize];
+    let mut queue = VecDeque::<(isize, isize)>::new();
+    visited[index(seed)] = true;
+    queue.push_back(seed);
+    while let Some(p) = queue.pop_front(){
+        points.push((p.0, p.1, true));
+        if points.len() > limit{
+            return Err(format!("Region is larger than {} cells, close the outline before filling", limit));
+        }
+        for next in [(p.0 + 1, p.1), (p.0 - 1, p.1), (p.0, p.1 + 1), (p.0, p.1 - 1)]{
+            if in_bounds(next) && !visited[index(next)] && !solid(index(next)){
+                visited[index(next)] = true;
+                queue.push_back(next);
+            }
+        }
+    }
+    Ok(Blob::new(Mask::from_points(points.into_iter())))
+}
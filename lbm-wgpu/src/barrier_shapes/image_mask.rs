@@ -0,0 +1,255 @@
+use wasm_bindgen::prelude::*;
+
+use super::{Shape, mask::Mask};
+
+//Images with more pixels than this many lattices are rejected before anything is allocated for them
+const MAX_LATTICES_PER_IMAGE: usize = 16;
+
+#[wasm_bindgen]
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ImageFit{
+    Fit,
+    Stretch,
+}
+
+//Grayscale copy of the source image, 0.0 is black and 1.0 is white
+struct Luminance{
+    width: usize,
+    height: usize,
+    values: Vec<f32>,
+}
+
+pub struct ImageMask{
+    pub mask: Mask
+}
+
+impl Shape for ImageMask{
+    fn get_mask(&self) -> &Mask {
+        &self.mask
+    }
+}
+
+impl ImageMask{
+
+    //Dark pixels (luminance below threshold) become barrier cells unless invert is set
+    pub fn new(
+        bytes: &[u8],
+        threshold: f32,
+        invert: bool,
+        fit: ImageFit,
+        xdim: isize,
+        ydim: isize) -> Result<ImageMask, String>{
+
+            let limit = (xdim.max(1) as usize).saturating_mul(ydim.max(1) as usize).saturating_mul(MAX_LATTICES_PER_IMAGE);
+            let image = if bytes.starts_with(&[0x89, b'P', b'N', b'G']){
+                Self::decode_png(bytes, limit)?
+            } else if bytes.starts_with(b"P1") || bytes.starts_with(b"P4"){
+                Self::decode_pbm(bytes, limit)?
+            } else {
+                return Err("Barrier images must be PNG or PBM".to_string());
+            };
+
+            if image.width == 0 || image.height == 0{
+                return Err("Barrier image is empty".to_string());
+            }
+
+            let (scale_x, scale_y) = match fit {
+                ImageFit::Stretch => (xdim as f64 / image.width as f64, ydim as f64 / image.height as f64),
+                ImageFit::Fit => {
+                    let scale = (xdim as f64 / image.width as f64).min(ydim as f64 / image.height as f64);
+                    (scale, scale)
+                }
+            };
+            let width = (image.width as f64 * scale_x).round() as isize;
+            let height = (image.height as f64 * scale_y).round() as isize;
+            let offset = ((xdim - width)/2, (ydim - height)/2);
+
+            let mut mask = Mask::new_empty();
+            mask.reserve(offset, (offset.0 + width - 1, offset.1 + height - 1));
+            for y in 0..height{
+                for x in 0..width{
+                    let source_x = (((x as f64 + 0.5) / scale_x) as usize).min(image.width - 1);
+                    let source_y = (((y as f64 + 0.5) / scale_y) as usize).min(image.height - 1);
+                    let solid = image.values[source_x + source_y * image.width] < threshold;
+                    if solid != invert{
+                        mask.set((x + offset.0, y + offset.1), true);
+                    }
+                }
+            }
+
+            Ok(
+                ImageMask{
+                    mask
+                }
+            )
+    }
+
+    fn decode_png(bytes: &[u8], limit: usize) -> Result<Luminance, String>{
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+        Self::check_size(reader.info().width as usize, reader.info().height as usize, limit)?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).map_err(|e| e.to_string())?;
+
+        let channels = info.color_type.samples();
+        let (width, height) = (info.width as usize, info.height as usize);
+        let mut values = Vec::<f32>::with_capacity(width * height);
+        for row in buffer.chunks(info.line_size).take(height){
+            for pixel in row.chunks(channels).take(width){
+                let pixel: Vec<f32> = pixel.iter().map(|p| *p as f32 / 255.0).collect();
+                //Transparent pixels are treated as white background
+                let value = match info.color_type {
+                    png::ColorType::Grayscale => pixel[0],
+                    png::ColorType::GrayscaleAlpha => pixel[0] * pixel[1] + 1.0 - pixel[1],
+                    png::ColorType::Rgb => Self::luma(pixel[0], pixel[1], pixel[2]),
+                    png::ColorType::Rgba => Self::luma(pixel[0], pixel[1], pixel[2]) * pixel[3] + 1.0 - pixel[3],
+                    png::ColorType::Indexed => return Err("Indexed PNGs were not expanded".to_string()),
+                };
+                values.push(value);
+            }
+        }
+        Ok(Luminance{ width, height, values })
+    }
+
+    //Supports plain (P1) and raw (P4) bitmaps, where 1 is black
+    fn decode_pbm(bytes: &[u8], limit: usize) -> Result<Luminance, String>{
+        let raw = bytes.starts_with(b"P4");
+        let mut position = 2;
+        let mut header = Vec::<usize>::new();
+        while header.len() < 2{
+            header.push(Self::next_pbm_token(bytes, &mut position)?);
+        }
+        let (width, height) = (header[0], header[1]);
+        let pixels = Self::check_size(width, height, limit)?;
+        //Raw rows are packed 8 pixels to a byte after a single whitespace byte, plain pixels take a byte each
+        let row_bytes = width.div_ceil(8);
+        let needed = if raw { row_bytes * height + 1 } else { pixels };
+        if bytes.len() - position < needed{
+            return Err("PBM data is truncated".to_string());
+        }
+        let mut values = Vec::<f32>::with_capacity(pixels);
+
+        if raw{
+            position += 1;
+            let data = &bytes[position..position + row_bytes * height];
+            for row in data.chunks(row_bytes){
+                for x in 0..width{
+                    let bit = (row[x/8] >> (7 - x % 8)) & 1;
+                    values.push(if bit == 1 { 0.0 } else { 1.0 });
+                }
+            }
+        } else {
+            let digits = bytes[position..].iter().filter(|b| **b == b'0' || **b == b'1');
+            values.extend(digits.take(pixels).map(|b| if *b == b'1' { 0.0 } else { 1.0 }));
+            if values.len() < pixels{
+                return Err("PBM data is truncated".to_string());
+            }
+        }
+        Ok(Luminance{ width, height, values })
+    }
+
+    //Pixel count, checked against the limit so a corrupt header can't ask for a huge allocation
+    fn check_size(width: usize, height: usize, limit: usize) -> Result<usize, String>{
+        width.checked_mul(height)
+            .filter(|pixels| *pixels <= limit)
+            .ok_or_else(|| format!("Barrier image of {} x {} pixels is too large", width, height))
+    }
+
+    fn next_pbm_token(bytes: &[u8], position: &mut usize) -> Result<usize, String>{
+        while *position < bytes.len(){
+            match bytes[*position] {
+                b'#' => while *position < bytes.len() && bytes[*position] != b'\n' { *position += 1 },
+                b if b.is_ascii_whitespace() => *position += 1,
+                _ => break,
+            }
+        }
+        let start = *position;
+        while *position < bytes.len() && bytes[*position].is_ascii_digit(){
+            *position += 1;
+        }
+        std::str::from_utf8(&bytes[start..*position])
+            .ok()
+            .and_then(|t| t.parse::<usize>().ok())
+            .ok_or_else(|| "PBM header is invalid".to_string())
+    }
+
+    fn luma(r: f32, g: f32, b: f32) -> f32{
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn solid(image: &ImageMask) -> Vec<(isize, isize)>{
+        image.mask.points().filter(|p| p.2).map(|p| (p.0, p.1)).collect()
+    }
+
+    fn png(width: u32, height: u32, pixels: &[u8]) -> Vec<u8>{
+        let mut bytes = Vec::<u8>::new();
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(pixels).unwrap();
+        bytes
+    }
+
+    fn crc(data: &[u8]) -> u32{
+        let mut crc = !0_u32;
+        for byte in data{
+            crc ^= *byte as u32;
+            for _ in 0..8{
+                crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+            }
+        }
+        !crc
+    }
+
+    #[test]
+    fn plain_pbm(){
+        let image = ImageMask::new(b"P1\n# comment\n3 2\n1 0 1\n0 1 0\n", 0.5, false, ImageFit::Stretch, 3, 2).unwrap();
+        assert_eq!(solid(&image), vec![(0, 0), (2, 0), (1, 1)]);
+        let inverted = ImageMask::new(b"P1 3 2 101010", 0.5, true, ImageFit::Stretch, 3, 2).unwrap();
+        assert_eq!(solid(&inverted), vec![(1, 0), (0, 1), (2, 1)]);
+    }
+
+    #[test]
+    fn raw_pbm(){
+        let image = ImageMask::new(b"P4\n10 2\n\xc0\x40\x00\x80", 0.5, false, ImageFit::Stretch, 10, 2).unwrap();
+        assert_eq!(solid(&image), vec![(0, 0), (1, 0), (9, 0), (8, 1)]);
+    }
+
+    #[test]
+    fn truncated_and_oversized_pbm(){
+        assert!(ImageMask::new(b"P1 3 2 1 0 1", 0.5, false, ImageFit::Stretch, 3, 2).is_err());
+        assert!(ImageMask::new(b"P4 16 2\n\xff", 0.5, false, ImageFit::Stretch, 16, 2).is_err());
+        let huge = ImageMask::new(b"P4 4294967295 4294967295\n", 0.5, false, ImageFit::Stretch, 16, 2);
+        assert!(huge.err().unwrap().contains("too large"));
+    }
+
+    #[test]
+    fn png_is_fitted_to_the_lattice(){
+        let bytes = png(2, 2, &[0, 255, 255, 0]);
+        let image = ImageMask::new(&bytes, 0.5, false, ImageFit::Fit, 8, 4).unwrap();
+        assert_eq!(solid(&image), vec![(2, 0), (3, 0), (2, 1), (3, 1), (4, 2), (5, 2), (4, 3), (5, 3)]);
+    }
+
+    #[test]
+    fn oversized_png_is_rejected_before_decoding(){
+        let mut bytes = png(2, 2, &[0; 4]);
+        //IHDR data starts after the signature and the chunk's length and type
+        bytes[16..20].copy_from_slice(&100_000_u32.to_be_bytes());
+        bytes[20..24].copy_from_slice(&100_000_u32.to_be_bytes());
+        let checksum = crc(&bytes[12..29]);
+        bytes[29..33].copy_from_slice(&checksum.to_be_bytes());
+        let error = ImageMask::new(&bytes, 0.5, false, ImageFit::Fit, 100, 100).err().unwrap();
+        assert!(error.contains("too large"), "{}", error);
+    }
+
+    #[test]
+    fn unknown_formats_are_rejected(){
+        assert!(ImageMask::new(b"GIF89a", 0.5, false, ImageFit::Fit, 10, 10).is_err());
+    }
+}
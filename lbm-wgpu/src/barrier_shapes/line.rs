@@ -1,157 +1,62 @@
-use std::collections::HashSet;
-use::line_drawing::Bresenham;
-use web_sys::console;
-
-use super::Shape;
+use super::{Shape, brush::Brush, mask::Mask};
 
 pub struct Line{
-    pub points: HashSet<(isize, isize, bool)>
+    pub mask: Mask
 }
 
 impl Shape for Line{
-    fn get_points(&self) -> &HashSet<(isize, isize, bool)> {
-        &self.points
-    }
-
-    fn is_empty(&self) -> bool {
-        self.points.is_empty()
+    fn get_mask(&self) -> &Mask {
+        &self.mask
     }
 } 
 
 impl Line{
-    pub fn new(
+    //Line drawn (or erased when value is false) with the given brush, clipped to the lattice
+    pub fn with_brush(
         end_point_1: (isize, isize), 
         end_point_2: (isize, isize), 
+        brush: &Brush,
+        value: bool,
         xdim: isize, 
-        ydim: isize) -> Result<Line, String>{
-            
-            if !Self::validate(end_point_1, end_point_2, xdim, ydim){
-                console::error_1(&format!("Endpoints ({},{}) ({},{}) are invalid with dimensions {} and {}", end_point_1.0, end_point_1.1,  end_point_2.0, end_point_2.1,  xdim, ydim).into());
-                return Err(format!("Endpoints ({},{}) ({},{}) are invalid with dimensions {} and {}", end_point_1.0, end_point_1.1,  end_point_2.0, end_point_2.1,  xdim, ydim));
-            }
-            
-            let mut points = HashSet::<(isize, isize, bool)>::new();
-
-            for point in Self::generate_endpoints(end_point_1, end_point_2).iter(){
-                if Self::validate(point.0, point.1, xdim, ydim){
-                    let mut previous = point.0.clone();
-                    for i in Bresenham::new(point.0,  point.1){
-                        points.insert((i.0, i.1, true));
-                        if Self::diagonal_step(previous,i){
-                            let difference = (i.0 - previous.0, i.1 - previous.1);
-                            points.insert((i.0 - difference.0, i.1, true));
-                            points.insert((i.0, i.1 - difference.1, true));
-                        }
-                        previous = i;
-                    }
-                }
+        ydim: isize) -> Line{
+            let mut mask = Mask::new_empty();
+            brush.stroke(end_point_1, end_point_2, value, xdim, ydim, &mut mask);
+            Line{
+                mask
             }
-            Ok(
-                Line{
-                    points
-                }
-            )
-    }
-
-    pub fn new_erased(
-        end_point_1: (isize, isize), 
-        end_point_2: (isize, isize), 
-        xdim: isize, 
-        ydim: isize) -> Result<Line, String>{
-            if !Self::validate(end_point_1, end_point_2, xdim, ydim){
-                console::log_1(&format!("Endpoints ({},{}) ({},{}) are invalid with dimensions {} and {}", end_point_1.0, end_point_1.1,  end_point_2.0, end_point_2.1,  xdim, ydim).into());
-                return Err(format!("Endpoints ({},{}) ({},{}) are invalid with dimensions {} and {}", end_point_1.0, end_point_1.1,  end_point_2.0, end_point_2.1,  xdim, ydim));
-            }
-            
-            let mut points = HashSet::<(isize, isize, bool)>::new();
-
-            for point in Self::generate_endpoints_variable(end_point_1, end_point_2, 30).iter(){
-                if Self::validate(point.0, point.1, xdim, ydim){
-                    let mut previous = point.0.clone();
-                    for i in Bresenham::new(point.0,  point.1){
-                        points.insert((i.0, i.1, false));
-                        if Self::diagonal_step(previous,i){
-                            let difference = (i.0 - previous.0, i.1 - previous.1);
-                            points.insert((i.0 - difference.0, i.1, false));
-                            points.insert((i.0, i.1 - difference.1, false));
-                        }
-                        previous = i;
-                    }
-                }
-            }
-            Ok(
-                Line{
-                    points
-                }
-            )
-    }
-
-    fn diagonal_step(previous_point: (isize, isize),
-                     next_point: (isize, isize)) -> bool{
-        previous_point.0 - next_point.0 != 0 && previous_point.1 - next_point.1 != 0
     }
 
-    fn generate_endpoints( end_point_1: (isize, isize), end_point_2: (isize, isize)) -> Vec<((isize, isize), (isize, isize))>{
-        let mut output = Vec::<((isize, isize), (isize, isize))>::new();
-        output.push(Self::order(end_point_1, end_point_2));
-
-        let left_endpoints = if output[0].0.1 > output[0].1.1 {
-                ((output[0].0.0, output[0].0.1 - 1),(output[0].0.0 + 1, output[0].0.1))
-            }else{
-                ((output[0].0.0, output[0].0.1 + 1),(output[0].0.0 + 1, output[0].0.1))
-            };
-
-        let right_endpoints = if output[0].0.1 > output[0].1.1 {
-                ((output[0].1.0 - 1, output[0].1.1),(output[0].1.0, output[0].1.1 - 1))
-            } else {
-                ((output[0].1.0 - 1, output[0].1.1),(output[0].1.0, output[0].1.1 - 1))
-            };
-            
-        output.push((left_endpoints.0, right_endpoints.0));
-        output.push((left_endpoints.1, right_endpoints.1));
-
-        output
-    }
-
-    fn generate_endpoints_variable( end_point_1: (isize, isize), end_point_2: (isize, isize), thickness: usize) -> Vec<((isize, isize), (isize, isize))>{
-        let mut output = Vec::<((isize, isize), (isize, isize))>::new();
-        output.push(Self::order(end_point_1, end_point_2));
-
-        let mut left_endpoints = Vec::<((isize, isize),(isize, isize))>::new();
-        let mut right_endpoints = Vec::<((isize, isize),(isize, isize))>::new();
-
-        for i in 1..thickness{
-            let i = i as isize;
-            if output[0].0.1 > output[0].1.1 { 
-                left_endpoints.push(((output[0].0.0, output[0].0.1 - i),(output[0].0.0 + i, output[0].0.1)));
-                right_endpoints.push(((output[0].1.0 - i, output[0].1.1),(output[0].1.0, output[0].1.1 - i))); 
-            } else {
-                left_endpoints.push(((output[0].0.0, output[0].0.1 + i),(output[0].0.0 + i, output[0].0.1)));
-                right_endpoints.push(((output[0].1.0 - i, output[0].1.1),(output[0].1.0, output[0].1.1 - i)));
-            }
+    //Nearest grid intersection with the given spacing in cells, spacings of 0 and 1 leave the point as is
+    pub fn snap_to_grid(point: (isize, isize), spacing: u32, xdim: isize, ydim: isize) -> (isize, isize){
+        if spacing <= 1{
+            return point;
         }
-
-        for i in 0..left_endpoints.len(){
-            output.push((left_endpoints[i].0, right_endpoints[i].0));
-            output.push((left_endpoints[i].1, right_endpoints[i].1));
+        let spacing = spacing as f64;
+        let snap = |v: isize, max: isize| (((v as f64 / spacing).round() * spacing) as isize).clamp(0, max - 1);
+        (snap(point.0, xdim), snap(point.1, ydim))
+    }
+
+    //Turns the end about the start to the nearest multiple of step degrees keeping the length, shortened
+    //if needed so the end stays on the lattice
+    pub fn snap_angle(start: (isize, isize), end: (isize, isize), step: f64, xdim: isize, ydim: isize) -> (isize, isize){
+        let (dx, dy) = ((end.0 - start.0) as f64, (end.1 - start.1) as f64);
+        let length = (dx * dx + dy * dy).sqrt();
+        if length == 0.0 || step <= 0.0{
+            return end;
         }
-
-        output
-    }
-
-    fn order(point1: (isize, isize), point2: (isize, isize)) -> ((isize, isize),(isize, isize)){
-        if point1.0 > point2.0{
-            return (point1, point2);
-        }
-        (point2, point1)
+        let angle = ((dy.atan2(dx).to_degrees() / step).round() * step).to_radians();
+        let (sin, cos) = angle.sin_cos();
+        let reach = |from: isize, direction: f64, max: isize| {
+            if direction > 1e-9 { (max - 1 - from) as f64 / direction }
+            else if direction < -1e-9 { from as f64 / -direction }
+            else { f64::INFINITY }
+        };
+        let length = length.min(reach(start.0, cos, xdim)).min(reach(start.1, sin, ydim));
+        ((start.0 as f64 + length * cos).round() as isize, (start.1 as f64 + length * sin).round() as isize)
     }
 
     pub fn join(&mut self, shape: Box<dyn Shape>){
-        self.points.extend(shape.get_points())
-    }
-
-    fn validate(ep1: (isize, isize), ep2: (isize, isize), xdim: isize, ydim: isize) -> bool{
-        ep1.0 >= 0 && ep2.0 >= 0 && ep1.1 >= 0 && ep2.1 >=0 && ep1.0 < xdim && ep2.0 < xdim && ep1.1 < ydim && ep2.1 < ydim
+        self.mask.join(&*shape)
     }
 
 }
\ No newline at end of file
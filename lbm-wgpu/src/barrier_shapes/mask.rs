@@ -0,0 +1,235 @@
+use super::Shape;
+
+//Dense form of a shape, and what every shape stores: a bounding rectangle with one bit per cell saying whether
+//the shape touches the cell and one saying whether it draws (rather than erases) it. Bits run row by row through
+//the rectangle, packed into u32 words so they can be uploaded as is
+#[derive(Clone, Debug, Default)]
+pub struct Mask{
+    origin: (isize, isize),
+    width: usize,
+    height: usize,
+    covered: Vec<u32>,
+    solid: Vec<u32>,
+}
+
+impl Mask{
+
+    pub fn new_empty() -> Mask{
+        Mask::default()
+    }
+
+    //Later points win where the same cell appears twice
+    pub fn from_points<I: Iterator<Item = (isize, isize, bool)> + Clone>(points: I) -> Mask{
+        let mut mask = Mask::new_empty();
+        if let Some(bounds) = Self::bounds_of(points.clone()){
+            mask.grow(bounds);
+            for p in points{
+                mask.set_unchecked((p.0, p.1), p.2);
+            }
+        }
+        mask
+    }
+
+    pub fn is_empty(&self) -> bool{
+        !self.covered.iter().any(|w| *w != 0)
+    }
+
+    //Number of touched cells
+    pub fn len(&self) -> usize{
+        self.covered.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    //Touched cells as (x, y, solid), row by row
+    pub fn points(&self) -> impl Iterator<Item = (isize, isize, bool)> + '_{
+        (0..self.width * self.height).filter(|i| get_bit(&self.covered, *i)).map(|i| {
+            (self.origin.0 + (i % self.width) as isize, self.origin.1 + (i / self.width) as isize, get_bit(&self.solid, i))
+        })
+    }
+
+    //Top left cell and size of the rectangle holding every touched cell
+    pub fn rectangle(&self) -> ((isize, isize), usize, usize){
+        (self.origin, self.width, self.height)
+    }
+
+    pub fn words(&self) -> (&[u32], &[u32]){
+        (&self.covered, &self.solid)
+    }
+
+    pub fn get(&self, cell: (isize, isize)) -> Option<bool>{
+        let i = self.bit(cell)?;
+        if get_bit(&self.covered, i) { Some(get_bit(&self.solid, i)) } else { None }
+    }
+
+    //Setting cells outside the rectangle grows it, so callers that know the bounds up front reserve them first
+    //rather than copying the bits over again for every cell that lands further out
+    pub fn set(&mut self, cell: (isize, isize), value: bool){
+        self.grow((cell, cell));
+        self.set_unchecked(cell, value);
+    }
+
+    //Grows the rectangle to hold every cell from min to max, both included
+    pub fn reserve(&mut self, min: (isize, isize), max: (isize, isize)){
+        if min.0 <= max.0 && min.1 <= max.1{
+            self.grow((min, max));
+        }
+    }
+
+    //Draws or erases every cell the shape touches
+    pub fn join(&mut self, shape: &dyn Shape){
+        self.join_mask(shape.get_mask());
+    }
+
+    pub fn join_mask(&mut self, other: &Mask){
+        if other.width == 0{
+            return;
+        }
+        //Same rectangle, so the bits line up word for word
+        if (self.origin, self.width, self.height) == (other.origin, other.width, other.height){
+            for (i, covered) in other.covered.iter().enumerate(){
+                self.covered[i] |= covered;
+                self.solid[i] = (self.solid[i] & !covered) | (other.solid[i] & covered);
+            }
+            return;
+        }
+        self.grow(other.bounds());
+        for row in 0..other.height{
+            for column in 0..other.width{
+                let i = row * other.width + column;
+                if get_bit(&other.covered, i){
+                    self.set_unchecked((other.origin.0 + column as isize, other.origin.1 + row as isize), get_bit(&other.solid, i));
+                }
+            }
+        }
+    }
+
+    //Cell by cell combination over the rectangle holding both masks, f maps the (covered, solid) words of self
+    //and other to the result's. Bits past the last cell are zero going in and have to stay that way
+    pub fn combine(&self, other: &Mask, f: impl Fn((u32, u32), (u32, u32)) -> (u32, u32)) -> Mask{
+        let (mut first, mut second) = (self.clone(), other.clone());
+        if other.width != 0{
+            first.grow(other.bounds());
+        }
+        if first.width == 0{
+            return first;
+        }
+        second.grow(first.bounds());
+        let words = first.covered.iter().zip(first.solid.iter()).zip(second.covered.iter().zip(second.solid.iter()))
+            .map(|((c1, s1), (c2, s2))| f((*c1, *s1), (*c2, *s2)));
+        let (covered, solid) = words.map(|(covered, solid)| (covered, solid & covered)).unzip();
+        Mask{ covered, solid, ..first }
+    }
+
+    //Erases every cell the other mask touches
+    pub fn erase(&mut self, other: &Mask){
+        let mut erased = other.clone();
+        erased.solid.iter_mut().for_each(|w| *w = 0);
+        self.join_mask(&erased);
+    }
+
+    pub fn empty(&mut self){
+        *self = Mask::new_empty();
+    }
+
+    //Same cells with drawing and erasing swapped
+    pub fn negate(&self) -> Mask{
+        let mut negated = self.clone();
+        for (solid, covered) in negated.solid.iter_mut().zip(negated.covered.iter()){
+            *solid = !*solid & covered;
+        }
+        negated
+    }
+
+    pub fn translate(&self, dx: isize, dy: isize) -> Mask{
+        let mut moved = self.clone();
+        moved.origin = (self.origin.0 + dx, self.origin.1 + dy);
+        moved
+    }
+
+    //Part of the mask inside a width x height lattice
+    pub fn clip(&self, xdim: isize, ydim: isize) -> Mask{
+        let (min, max) = self.bounds();
+        let (min, max) = ((min.0.max(0), min.1.max(0)), (max.0.min(xdim - 1), max.1.min(ydim - 1)));
+        if (min, max) == self.bounds(){
+            return self.clone();
+        }
+        let mut clipped = Mask::new_empty();
+        if min.0 > max.0 || min.1 > max.1{
+            return clipped;
+        }
+        clipped.grow((min, max));
+        for y in min.1..=max.1{
+            for x in min.0..=max.0{
+                if let Some(value) = self.get((x, y)){
+                    clipped.set_unchecked((x, y), value);
+                }
+            }
+        }
+        clipped
+    }
+
+    fn bounds(&self) -> ((isize, isize), (isize, isize)){
+        (self.origin, (self.origin.0 + self.width as isize - 1, self.origin.1 + self.height as isize - 1))
+    }
+
+    fn bounds_of<I: Iterator<Item = (isize, isize, bool)>>(points: I) -> Option<((isize, isize), (isize, isize))>{
+        points.fold(None, |bounds, p| Some(match bounds {
+            Some((min, max)) => ((p.0.min(min.0), p.1.min(min.1)), (p.0.max(max.0), p.1.max(max.1))),
+            None => ((p.0, p.1), (p.0, p.1)),
+        }))
+    }
+
+    fn bit(&self, cell: (isize, isize)) -> Option<usize>{
+        let (x, y) = (cell.0 - self.origin.0, cell.1 - self.origin.1);
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height{
+            return None;
+        }
+        Some(x as usize + y as usize * self.width)
+    }
+
+    fn set_unchecked(&mut self, cell: (isize, isize), value: bool){
+        let i = (cell.0 - self.origin.0) as usize + (cell.1 - self.origin.1) as usize * self.width;
+        set_bit(&mut self.covered, i, true);
+        set_bit(&mut self.solid, i, value);
+    }
+
+    //Enlarges the rectangle to hold the bounds, copying the existing bits across once
+    fn grow(&mut self, bounds: ((isize, isize), (isize, isize))){
+        let (min, max) = if self.width == 0 {
+            bounds
+        } else {
+            let current = self.bounds();
+            ((bounds.0.0.min(current.0.0), bounds.0.1.min(current.0.1)), (bounds.1.0.max(current.1.0), bounds.1.1.max(current.1.1)))
+        };
+        if self.width != 0 && (min, max) == self.bounds(){
+            return;
+        }
+        let (width, height) = ((max.0 - min.0 + 1) as usize, (max.1 - min.1 + 1) as usize);
+        let words = (width * height).div_ceil(32);
+        let mut grown = Mask{
+            origin: min,
+            width,
+            height,
+            covered: vec![0; words],
+            solid: vec![0; words],
+        };
+        for i in 0..self.width * self.height{
+            if get_bit(&self.covered, i){
+                let cell = (self.origin.0 + (i % self.width) as isize, self.origin.1 + (i / self.width) as isize);
+                grown.set_unchecked(cell, get_bit(&self.solid, i));
+            }
+        }
+        *self = grown;
+    }
+}
+
+fn get_bit(bits: &[u32], index: usize) -> bool{
+    bits[index / 32] >> (index % 32) & 1 == 1
+}
+
+fn set_bit(bits: &mut [u32], index: usize, value: bool){
+    if value{
+        bits[index / 32] |= 1 << (index % 32);
+    } else {
+        bits[index / 32] &= !(1 << (index % 32));
+    }
+}
@@ -1,22 +1,34 @@
-use std::collections::HashSet;
-
 use super::{Shape, blob::Blob};
 
+//The masks are combined a word at a time, covered bits say which cells a shape touches and solid bits
+//which of those it draws, so covered & !solid are the cells it erases
+
 pub fn merge(s1: &dyn Shape, s2: &dyn Shape) -> Blob{
-    let mut b = Blob::new(HashSet::<(isize, isize, bool)>::new());
+    let mut b = Blob::new_empty();
     b.join(s1);
     b.join(s2);
     b
 }
 
-pub fn get_points_vector(shape: &dyn Shape, xdim: usize) -> Vec<u32>{
-    shape.get_points().iter().flat_map(|x| vec![get_index(&x, xdim), get_value(&x)]).collect()
+//Cells drawn by both shapes stay solid, every other cell either shape draws is erased along with the cells
+//both erase, so the result can replace the two shapes
+pub fn intersection(s1: &dyn Shape, s2: &dyn Shape) -> Blob{
+    Blob::new(s1.get_mask().combine(s2.get_mask(), |(covered_1, solid_1), (covered_2, solid_2)| {
+        let solid = solid_1 & solid_2;
+        (solid_1 | solid_2 | (covered_1 & covered_2), solid)
+    }))
 }
 
-fn get_index(point: &(isize, isize, bool), xdim: usize) -> u32{
-    point.0 as u32 + point.1 as u32 * xdim as u32
+//Removes the cells s2 draws from s1, those cells are erased so the result can carve into existing barriers
+pub fn difference(s1: &dyn Shape, s2: &dyn Shape) -> Blob{
+    Blob::new(s1.get_mask().combine(s2.get_mask(), |(covered_1, solid_1), (_, solid_2)| {
+        (covered_1, solid_1 & !solid_2)
+    }))
 }
 
-fn get_value(point: &(isize, isize, bool)) ->  u32{
-    if point.2 == true { 1 } else { 0 }
-}
\ No newline at end of file
+//Cells drawn by exactly one shape stay solid, overlapping cells cancel and are erased
+pub fn symmetric_difference(s1: &dyn Shape, s2: &dyn Shape) -> Blob{
+    Blob::new(s1.get_mask().combine(s2.get_mask(), |(covered_1, solid_1), (covered_2, solid_2)| {
+        (covered_1 | covered_2, solid_1 ^ solid_2)
+    }))
+}
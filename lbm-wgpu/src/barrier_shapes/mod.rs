@@ -1,19 +1,63 @@
 use std::collections::HashSet;
 
 use self::blob::Blob;
+use self::mask::Mask;
+use self::transform::{Affine, MirrorAxis};
 
 pub mod line;
+pub mod brush;
 pub mod merge_shapes;
 pub mod blob;
+pub mod airfoil;
+pub mod circle;
+pub mod ellipse;
+pub mod rectangle;
+pub mod polygon;
 pub mod curve;
 pub mod curve_collection;
+pub mod image_mask;
+pub mod raster;
+pub mod svg;
+pub mod flood_fill;
+pub mod text;
+pub mod transform;
+pub mod mask;
+pub mod primitive;
 
 pub trait Shape {
-    fn get_points(&self) -> &HashSet<(isize, isize, bool)>;
-    fn is_empty(&self) -> bool;
+    fn get_mask(&self) -> &Mask;
+
+    fn is_empty(&self) -> bool {
+        self.get_mask().is_empty()
+    }
+
+    //Cells as (x, y, solid), for code that still works point by point
+    fn get_points(&self) -> HashSet<(isize, isize, bool)> {
+        self.get_mask().points().collect()
+    }
+
     fn negate(&self) -> Blob {
-        Blob{
-            points: self.get_points().into_iter().map(|x| (x.0, x.1, !x.2)).collect()
-        }
+        Blob::new(self.get_mask().negate())
+    }
+
+    fn translate(&self, dx: isize, dy: isize, xdim: isize, ydim: isize) -> Blob {
+        Blob::new(self.get_mask().translate(dx, dy).clip(xdim, ydim))
+    }
+
+    //Degrees clockwise on screen about pivot, in lattice cells
+    fn rotate(&self, degrees: f64, pivot: (f64, f64), xdim: isize, ydim: isize) -> Blob {
+        transform::transform(self, &Affine::rotation(degrees, pivot), xdim, ydim)
+    }
+
+    fn scale(&self, factor: (f64, f64), pivot: (f64, f64), xdim: isize, ydim: isize) -> Blob {
+        transform::transform(self, &Affine::scale(factor, pivot), xdim, ydim)
+    }
+
+    fn mirror(&self, axis: MirrorAxis, position: f64, xdim: isize, ydim: isize) -> Blob {
+        transform::transform(self, &Affine::mirror(axis, position), xdim, ydim)
+    }
+
+    fn transform(&self, affine: &Affine, xdim: isize, ydim: isize) -> Blob {
+        transform::transform(self, affine, xdim, ydim)
     }
 }
\ No newline at end of file
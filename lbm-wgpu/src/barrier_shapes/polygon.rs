@@ -0,0 +1,30 @@
+use super::{Shape, raster, mask::Mask};
+
+pub struct Polygon{
+    pub mask: Mask
+}
+
+impl Shape for Polygon{
+    fn get_mask(&self) -> &Mask {
+        &self.mask
+    }
+}
+
+impl Polygon{
+    //Scanline fills the polygon and draws its edges so thin slivers stay solid
+    pub fn new(vertices: &[(f64, f64)], xdim: isize, ydim: isize) -> Polygon{
+        //Vertices are cell indices, shift them to cell centers before sampling
+        let centered: Vec<(f64, f64)> = vertices.iter().map(|v| (v.0 + 0.5, v.1 + 0.5)).collect();
+        let mut mask = Mask::new_empty();
+        raster::fill(std::slice::from_ref(&centered), true, xdim, ydim, &mut mask);
+        raster::stroke(&centered, true, true, xdim, ydim, &mut mask);
+        Polygon{
+            mask: mask.clip(xdim, ydim)
+        }
+    }
+
+    pub fn from_cells(vertices: &[(isize, isize)], xdim: isize, ydim: isize) -> Polygon{
+        let vertices: Vec<(f64, f64)> = vertices.iter().map(|v| (v.0 as f64, v.1 as f64)).collect();
+        Self::new(&vertices, xdim, ydim)
+    }
+}
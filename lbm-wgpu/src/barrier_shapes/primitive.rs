@@ -0,0 +1,219 @@
+use std::mem;
+
+use super::{raster, mask::Mask};
+
+//Shapes simple enough to be rasterized on the GPU straight from their parameters, in lattice cells with cell
+//centers on integer coordinates. See LBM::draw_primitives
+#[derive(Clone, Debug, PartialEq)]
+pub enum Primitive{
+    //Capsule around the segment, as left by a round brush of the given width
+    Segment{
+        start: (f64, f64),
+        end: (f64, f64),
+        width: f64,
+    },
+    Circle{
+        center: (f64, f64),
+        radius: f64,
+    },
+    //Filled with its edges drawn, like Polygon::new
+    Polygon{
+        vertices: Vec<(f64, f64)>,
+    },
+}
+
+//Narrower segments would let diagonal runs of cells break apart
+const MIN_HALF_WIDTH: f64 = 0.75;
+
+//Points are snapped to multiples of 1/SUBCELLS of a cell and every test after that is done on integers, so the
+//CPU and barrier_primitives.wgsl pick the same cells however the GPU rounds floats
+const SUBCELLS: f64 = 2.0;
+
+//Polygons and segments are clipped to the lattice with this many cells to spare, past a segment's half width,
+//before they are snapped. That leaves the cells on the lattice as they were and keeps the products in the tests
+//within an i32 for lattices up to 10000 cells a side, given segments no wider than a brush fitted to the lattice
+const CLIP_MARGIN: f64 = 2.0;
+
+//The primitive as barrier_primitives.wgsl sees it, points in subcells
+struct Snapped{
+    kind: u32,
+    a: (i32, i32),
+    b: (i32, i32),
+    //Largest squared distance in subcells a covered cell can be from the segment, the circle's center or a
+    //polygon edge
+    limit: u32,
+    vertices: Vec<(i32, i32)>,
+}
+
+impl Primitive{
+
+    //Cells the primitive can touch as (first cell, width, height) clipped to the lattice, None if it misses
+    pub fn bounds(&self, xdim: u32, ydim: u32) -> Option<((u32, u32), u32, u32)>{
+        self.snap(xdim, ydim).bounds(xdim, ydim)
+    }
+
+    //Packs the primitive into the 16 words barrier_primitives.wgsl reads, polygon vertices are appended to
+    //vertices. Returns None if the primitive lies outside the lattice
+    pub fn encode(&self, value: bool, xdim: u32, ydim: u32, vertices: &mut Vec<i32>) -> Option<[u32; 16]>{
+        let snapped = self.snap(xdim, ydim);
+        let ((x, y), width, height) = snapped.bounds(xdim, ydim)?;
+        let mut words = [0_u32; 16];
+        words[0] = snapped.kind;
+        words[1] = u32::from(value);
+        words[2] = (vertices.len()/2) as u32;
+        words[3] = snapped.vertices.len() as u32;
+        words[4..8].copy_from_slice(&[x, y, width, height]);
+        words[8..12].copy_from_slice(&[snapped.a.0, snapped.a.1, snapped.b.0, snapped.b.1].map(|v| v as u32));
+        words[12] = snapped.limit;
+        vertices.extend(snapped.vertices.iter().flat_map(|v| [v.0, v.1]));
+        Some(words)
+    }
+
+    //Cells barrier_primitives.wgsl sets for the primitive, the same integer tests on the same snapped points so
+    //the undo history records exactly what the GPU drew
+    pub fn rasterize(&self, value: bool, xdim: u32, ydim: u32) -> Mask{
+        let mut mask = Mask::new_empty();
+        let snapped = self.snap(xdim, ydim);
+        let ((x, y), width, height) = match snapped.bounds(xdim, ydim) {
+            Some(b) => b,
+            None => return mask,
+        };
+        mask.reserve((x as isize, y as isize), ((x + width - 1) as isize, (y + height - 1) as isize));
+        for cy in y..y + height{
+            for cx in x..x + width{
+                if snapped.covers(cx as i32, cy as i32){
+                    mask.set((cx as isize, cy as isize), value);
+                }
+            }
+        }
+        mask
+    }
+
+    fn snap(&self, xdim: u32, ydim: u32) -> Snapped{
+        let snap = |v: (f64, f64)| ((v.0 * SUBCELLS).round() as i32, (v.1 * SUBCELLS).round() as i32);
+        //Squared distance in subcells, floored since the distances it is compared with are whole
+        let limit = |distance: f64| (distance * SUBCELLS).powi(2).floor().min(u32::MAX as f64) as u32;
+        let area = |reach: f64| ((-reach, -reach), (xdim as f64 - 1.0 + reach, ydim as f64 - 1.0 + reach));
+        let mut snapped = Snapped{ kind: 0, a: (0, 0), b: (0, 0), limit: 0, vertices: Vec::new() };
+        match self {
+            Primitive::Segment { start, end, width } => {
+                let half_width = (width/2.0).max(MIN_HALF_WIDTH);
+                let (low, high) = area(half_width + CLIP_MARGIN);
+                //A segment missing the lattice is left with no limit, so it still covers nothing once snapped
+                if let Some((a, b)) = raster::clip_segment(*start, *end, low, high){
+                    (snapped.a, snapped.b, snapped.limit) = (snap(a), snap(b), limit(half_width));
+                }
+            },
+            Primitive::Circle { center, radius } => {
+                //Every circle comes centered on a cell, pulling stray centers in keeps the distances small
+                let (low, high) = area(CLIP_MARGIN);
+                snapped.kind = 1;
+                snapped.a = snap((center.0.clamp(low.0, high.0), center.1.clamp(low.1, high.1)));
+                snapped.limit = limit(radius.max(0.0));
+            },
+            Primitive::Polygon { vertices } => {
+                let (low, high) = area(CLIP_MARGIN);
+                snapped.kind = 2;
+                snapped.vertices = clip_polygon(vertices, low, high).into_iter().map(snap).collect();
+                //Cells within half a cell of an edge count as inside
+                snapped.limit = limit(0.5);
+            },
+        }
+        snapped
+    }
+}
+
+impl Snapped{
+
+    fn bounds(&self, xdim: u32, ydim: u32) -> Option<((u32, u32), u32, u32)>{
+        let points: &[(i32, i32)] = match self.kind {
+            0 if self.limit > 0 => &[self.a, self.b],
+            1 => &[self.a],
+            2 => &self.vertices,
+            _ => &[],
+        };
+        let first = points.first()?;
+        let (min, max) = points.iter().fold((*first, *first), |(min, max), p| ((min.0.min(p.0), min.1.min(p.1)), (max.0.max(p.0), max.1.max(p.1))));
+        //Farthest a covered cell can be from the points, in subcells. Cell c sits at c * SUBCELLS
+        let reach = (self.limit as f64).sqrt().ceil() as i64;
+        let subcells = SUBCELLS as i64;
+        let first = |v: i32| -((reach - v as i64).div_euclid(subcells));
+        let last = |v: i32| (v as i64 + reach).div_euclid(subcells);
+        let (min_x, min_y) = (first(min.0).max(0), first(min.1).max(0));
+        let (max_x, max_y) = (last(max.0).min(xdim as i64 - 1), last(max.1).min(ydim as i64 - 1));
+        if min_x > max_x || min_y > max_y{
+            return None;
+        }
+        Some(((min_x as u32, min_y as u32), (max_x - min_x) as u32 + 1, (max_y - min_y) as u32 + 1))
+    }
+
+    fn covers(&self, x: i32, y: i32) -> bool{
+        let p = (x * SUBCELLS as i32, y * SUBCELLS as i32);
+        match self.kind {
+            0 => segment_covers(p, self.a, self.b, self.limit),
+            1 => squared_length((p.0 - self.a.0, p.1 - self.a.1)) <= self.limit,
+            //Even-odd rule
+            _ => {
+                let mut inside = false;
+                for (i, a) in self.vertices.iter().enumerate(){
+                    let b = self.vertices[(i + self.vertices.len() - 1) % self.vertices.len()];
+                    if segment_covers(p, *a, b, self.limit){
+                        return true;
+                    }
+                    //p lies left of where the edge crosses its row, compared without dividing
+                    let (left, right) = ((p.0 - a.0) * (b.1 - a.1), (p.1 - a.1) * (b.0 - a.0));
+                    if (a.1 > p.1) != (b.1 > p.1) && (if b.1 > a.1 { left < right } else { left > right }){
+                        inside = !inside;
+                    }
+                }
+                inside
+            },
+        }
+    }
+}
+
+//Whether p is within the square root of limit of the segment from a to b, past the ends that is the distance to
+//the nearest end and in between cross(ap, ab)^2 / |ab|^2, which needs 64 bits once multiplied out
+fn segment_covers(p: (i32, i32), a: (i32, i32), b: (i32, i32), limit: u32) -> bool{
+    let (ap, ab) = ((p.0 - a.0, p.1 - a.1), (b.0 - a.0, b.1 - a.1));
+    let along = ap.0 * ab.0 + ap.1 * ab.1;
+    let length = squared_length(ab);
+    if along <= 0{
+        return squared_length(ap) <= limit;
+    }
+    if along as u32 >= length{
+        return squared_length((p.0 - b.0, p.1 - b.1)) <= limit;
+    }
+    let cross = (ap.0 * ab.1 - ap.1 * ab.0).unsigned_abs() as u64;
+    cross * cross <= limit as u64 * length as u64
+}
+
+fn squared_length(v: (i32, i32)) -> u32{
+    (v.0 * v.0) as u32 + (v.1 * v.1) as u32
+}
+
+//Sutherland-Hodgman against the box from low to high, inside the box the even-odd rule gives the same cells
+//for the clipped polygon. Polygons with points that aren't finite come back empty
+fn clip_polygon(vertices: &[(f64, f64)], low: (f64, f64), high: (f64, f64)) -> Vec<(f64, f64)>{
+    if !vertices.iter().all(|v| v.0.is_finite() && v.1.is_finite()){
+        return Vec::new();
+    }
+    let mut clipped = vertices.to_vec();
+    //Each side of the box as (axis, bound, whether the inside lies above the bound)
+    for (axis, bound, above) in [(0, low.0, true), (0, high.0, false), (1, low.1, true), (1, high.1, false)]{
+        let input = mem::take(&mut clipped);
+        let coordinate = |v: &(f64, f64)| if axis == 0 { v.0 } else { v.1 };
+        let inside = |v: &(f64, f64)| if above { coordinate(v) >= bound } else { coordinate(v) <= bound };
+        for (i, current) in input.iter().enumerate(){
+            let previous = &input[(i + input.len() - 1) % input.len()];
+            if inside(current) != inside(previous){
+                let t = (bound - coordinate(previous)) / (coordinate(current) - coordinate(previous));
+                clipped.push((previous.0 + t * (current.0 - previous.0), previous.1 + t * (current.1 - previous.1)));
+            }
+            if inside(current){
+                clipped.push(*current);
+            }
+        }
+    }
+    clipped
+}
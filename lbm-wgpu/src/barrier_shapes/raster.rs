@@ -0,0 +1,162 @@
+use line_drawing::Bresenham;
+
+use super::mask::Mask;
+
+//Rasterizes a polyline in lattice coordinates, diagonal steps are padded so fluid can't leak through. Only the
+//part on the lattice is drawn
+pub fn stroke(path: &[(f64, f64)], closed: bool, value: bool, xdim: isize, ydim: isize, mask: &mut Mask){
+    stroke_within(path, closed, value, ((0, 0), (xdim - 1, ydim - 1)), mask);
+}
+
+//Stroke of the cells from min to max, both included. Segments are clipped to that area before they are walked,
+//so vertices far off the lattice cost no more than ones on it
+fn stroke_within(path: &[(f64, f64)], closed: bool, value: bool, area: ((isize, isize), (isize, isize)), mask: &mut Mask){
+    let (min, max) = area;
+    if min.0 > max.0 || min.1 > max.1{
+        return;
+    }
+    let (low, high) = ((min.0 as f64, min.1 as f64), ((max.0 + 1) as f64, (max.1 + 1) as f64));
+    let cell = |p: (f64, f64)| ((p.0.floor() as isize).clamp(min.0, max.0), (p.1.floor() as isize).clamp(min.1, max.1));
+    let mut ends: Vec<((f64, f64), (f64, f64))> = path.windows(2).map(|w| (w[0], w[1])).collect();
+    if closed && path.len() > 2{
+        ends.push((path[path.len() - 1], path[0]));
+    }
+    if path.len() == 1{
+        ends.push((path[0], path[0]));
+    }
+    let segments: Vec<((isize, isize), (isize, isize))> = ends.into_iter()
+        .filter_map(|(a, b)| clip_segment(a, b, low, high))
+        .map(|(a, b)| (cell(a), cell(b)))
+        .collect();
+    //Every cell of a segment, padding included, lies in the box spanned by its ends
+    if let Some(first) = segments.first(){
+        let (low, high) = segments.iter().fold((first.0, first.0), |(low, high), (a, b)| (
+            (low.0.min(a.0).min(b.0), low.1.min(a.1).min(b.1)),
+            (high.0.max(a.0).max(b.0), high.1.max(a.1).max(b.1))));
+        mask.reserve(low, high);
+    }
+    for (start, end) in segments{
+        let mut previous = start;
+        for i in Bresenham::new(start, end){
+            mask.set(i, value);
+            if previous.0 != i.0 && previous.1 != i.1{
+                mask.set((previous.0, i.1), value);
+                mask.set((i.0, previous.1), value);
+            }
+            previous = i;
+        }
+    }
+}
+
+//Part of the segment from a to b inside the box from low to high, None if it misses the box or isn't finite
+pub fn clip_segment(a: (f64, f64), b: (f64, f64), low: (f64, f64), high: (f64, f64)) -> Option<((f64, f64), (f64, f64))>{
+    if ![a.0, a.1, b.0, b.1].iter().all(|v| v.is_finite()){
+        return None;
+    }
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let (mut enter, mut exit) = (0.0f64, 1.0f64);
+    for (step, room) in [(-dx, a.0 - low.0), (dx, high.0 - a.0), (-dy, a.1 - low.1), (dy, high.1 - a.1)]{
+        if step == 0.0{
+            if room < 0.0{
+                return None;
+            }
+        } else if step < 0.0{
+            enter = enter.max(room / step);
+        } else {
+            exit = exit.min(room / step);
+        }
+    }
+    if enter > exit{
+        return None;
+    }
+    Some(((a.0 + enter * dx, a.1 + enter * dy), (a.0 + exit * dx, a.1 + exit * dy)))
+}
+
+//Fills the interior of the rings using the nonzero winding rule, sampling at cell centers
+pub fn fill(rings: &[Vec<(f64, f64)>], value: bool, xdim: isize, ydim: isize, mask: &mut Mask){
+    let edges: Vec<((f64, f64), (f64, f64))> = rings.iter()
+        .filter(|r| r.len() > 2)
+        .flat_map(|r| (0..r.len()).map(move |i| (r[i], r[(i + 1) % r.len()])))
+        .filter(|e| e.0.1 != e.1.1)
+        .collect();
+    if edges.is_empty(){
+        return;
+    }
+
+    let min_y = edges.iter().map(|e| e.0.1.min(e.1.1)).fold(f64::INFINITY, f64::min);
+    let max_y = edges.iter().map(|e| e.0.1.max(e.1.1)).fold(f64::NEG_INFINITY, f64::max);
+    let first_row = (min_y.floor() as isize).max(0);
+    let last_row = (max_y.ceil() as isize).min(ydim - 1);
+    let min_x = edges.iter().map(|e| e.0.0.min(e.1.0)).fold(f64::INFINITY, f64::min);
+    let max_x = edges.iter().map(|e| e.0.0.max(e.1.0)).fold(f64::NEG_INFINITY, f64::max);
+    mask.reserve(((min_x.floor() as isize).max(0), first_row), ((max_x.ceil() as isize).min(xdim - 1), last_row));
+
+    let mut crossings = Vec::<(f64, i32)>::new();
+    for row in first_row..=last_row{
+        let center = row as f64 + 0.5;
+        crossings.clear();
+        for (a, b) in edges.iter(){
+            let (low, high, direction) = if a.1 < b.1 { (a, b, 1) } else { (b, a, -1) };
+            if center >= low.1 && center < high.1{
+                let t = (center - low.1) / (high.1 - low.1);
+                crossings.push((low.0 + t * (high.0 - low.0), direction));
+            }
+        }
+        crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut winding = 0;
+        for i in 0..crossings.len(){
+            winding += crossings[i].1;
+            if winding != 0 && i + 1 < crossings.len(){
+                let start = ((crossings[i].0 - 0.5).ceil() as isize).max(0);
+                let end = ((crossings[i + 1].0 - 0.5).floor() as isize).min(xdim - 1);
+                for column in start..=end{
+                    mask.set((column, row), value);
+                }
+            }
+        }
+    }
+}
+
+//Stroke with a round pen of the given width in cells, widths of 1 or less give the thin stroke
+pub fn thick_stroke(path: &[(f64, f64)], closed: bool, width: f64, value: bool, xdim: isize, ydim: isize, mask: &mut Mask){
+    stamped_stroke(path, closed, &pen(width), value, xdim, ydim, mask);
+}
+
+//Stamps the offsets along the stroke, the centerline is only walked as far off the lattice as the offsets reach
+//back onto it
+pub fn stamped_stroke(path: &[(f64, f64)], closed: bool, offsets: &[(isize, isize)], value: bool, xdim: isize, ydim: isize, mask: &mut Mask){
+    let reach = offsets.iter().map(|o| o.0.abs().max(o.1.abs())).max().unwrap_or(0);
+    let mut centerline = Mask::new_empty();
+    stroke_within(path, closed, value, ((-reach, -reach), (xdim - 1 + reach, ydim - 1 + reach)), &mut centerline);
+    let mut stamped = Mask::new_empty();
+    stamp(&centerline, offsets, value, &mut stamped);
+    mask.join_mask(&stamped.clip(xdim, ydim));
+}
+
+//Sets every cell covered by the offsets placed on each cell of the centerline
+pub fn stamp(centerline: &Mask, offsets: &[(isize, isize)], value: bool, mask: &mut Mask){
+    let (origin, width, height) = centerline.rectangle();
+    if centerline.is_empty() || offsets.is_empty(){
+        return;
+    }
+    let low = offsets.iter().copied().fold((0, 0), |a, o| (a.0.min(o.0), a.1.min(o.1)));
+    let high = offsets.iter().copied().fold((0, 0), |a, o| (a.0.max(o.0), a.1.max(o.1)));
+    mask.reserve((origin.0 + low.0, origin.1 + low.1), (origin.0 + width as isize - 1 + high.0, origin.1 + height as isize - 1 + high.1));
+    for p in centerline.points(){
+        for o in offsets.iter(){
+            mask.set((p.0 + o.0, p.1 + o.1), value);
+        }
+    }
+}
+
+//Cell offsets covered by a round pen of the given width centered on a cell
+pub fn pen(width: f64) -> Vec<(isize, isize)>{
+    let radius = (width - 1.0).max(0.0)/2.0;
+    let extent = radius.ceil() as isize;
+    let limit = radius * radius + 0.25;
+    (-extent..=extent)
+        .flat_map(|y| (-extent..=extent).map(move |x| (x, y)))
+        .filter(|o| (o.0 * o.0 + o.1 * o.1) as f64 <= limit)
+        .collect()
+}
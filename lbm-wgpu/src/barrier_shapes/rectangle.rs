@@ -0,0 +1,59 @@
+use super::{Shape, polygon::Polygon, mask::Mask};
+
+pub struct Rectangle{
+    pub mask: Mask
+}
+
+impl Shape for Rectangle{
+    fn get_mask(&self) -> &Mask {
+        &self.mask
+    }
+}
+
+impl Rectangle{
+    //Axis aligned rectangle spanned by two opposite corners, both included
+    pub fn new(corner_1: (isize, isize), corner_2: (isize, isize), xdim: isize, ydim: isize) -> Rectangle{
+        let min = (corner_1.0.min(corner_2.0).max(0), corner_1.1.min(corner_2.1).max(0));
+        let max = (corner_1.0.max(corner_2.0).min(xdim - 1), corner_1.1.max(corner_2.1).min(ydim - 1));
+        let mut mask = Mask::new_empty();
+        mask.reserve(min, max);
+        for y in min.1..=max.1{
+            for x in min.0..=max.0{
+                mask.set((x, y), true);
+            }
+        }
+        Rectangle{
+            mask
+        }
+    }
+
+    //Rectangle of the given width and height rotated clockwise on screen by rotation degrees
+    pub fn rotated(center: (f64, f64), size: (f64, f64), rotation: f64, xdim: isize, ydim: isize) -> Rectangle{
+        let (sin, cos) = rotation.to_radians().sin_cos();
+        let (hw, hh) = (size.0/2.0, size.1/2.0);
+        let corners: Vec<(f64, f64)> = [(-hw, -hh), (hw, -hh), (hw, hh), (-hw, hh)].iter()
+            .map(|c| (center.0 + c.0 * cos - c.1 * sin, center.1 + c.0 * sin + c.1 * cos))
+            .collect();
+        Rectangle{
+            mask: Polygon::new(&corners, xdim, ydim).mask
+        }
+    }
+
+    //Rectangle with one edge from edge_start to edge_end, extended perpendicular to reach depth_point
+    pub fn from_edge(edge_start: (isize, isize), edge_end: (isize, isize), depth_point: (isize, isize), xdim: isize, ydim: isize) -> Rectangle{
+        let edge = ((edge_end.0 - edge_start.0) as f64, (edge_end.1 - edge_start.1) as f64);
+        let length = (edge.0 * edge.0 + edge.1 * edge.1).sqrt();
+        if length == 0.0{
+            return Self::new(edge_start, edge_start, xdim, ydim);
+        }
+        let normal = (-edge.1/length, edge.0/length);
+        let depth = (depth_point.0 - edge_start.0) as f64 * normal.0 + (depth_point.1 - edge_start.1) as f64 * normal.1;
+        let offset = (normal.0 * depth, normal.1 * depth);
+        let start = (edge_start.0 as f64, edge_start.1 as f64);
+        let end = (edge_end.0 as f64, edge_end.1 as f64);
+        let corners = [start, end, (end.0 + offset.0, end.1 + offset.1), (start.0 + offset.0, start.1 + offset.1)];
+        Rectangle{
+            mask: Polygon::new(&corners, xdim, ydim).mask
+        }
+    }
+}
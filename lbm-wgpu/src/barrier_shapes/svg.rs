@@ -0,0 +1,694 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+
+use super::{blob::Blob, image_mask::ImageFit, mask::Mask, raster};
+
+#[wasm_bindgen]
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum SvgMode{
+    Outline,
+    Fill,
+    //Fill unless fill is none (as an attribute or in style), outline when a stroke is set
+    Auto,
+}
+
+type Point = (f64, f64);
+
+//Affine matrix [a b c d e f] mapping (x, y) to (ax + cy + e, bx + dy + f)
+#[derive(Clone, Copy, Debug)]
+struct Transform([f64; 6]);
+
+impl Transform{
+    fn identity() -> Transform{
+        Transform([1.0, 0.0, 0.0, 1.0, 0.0, 0.0])
+    }
+
+    fn then(&self, outer: &Transform) -> Transform{
+        let [a, b, c, d, e, f] = self.0;
+        let [a2, b2, c2, d2, e2, f2] = outer.0;
+        Transform([
+            a2 * a + c2 * b,
+            b2 * a + d2 * b,
+            a2 * c + c2 * d,
+            b2 * c + d2 * d,
+            a2 * e + c2 * f + e2,
+            b2 * e + d2 * f + f2,
+        ])
+    }
+
+    fn apply(&self, p: Point) -> Point{
+        let [a, b, c, d, e, f] = self.0;
+        (a * p.0 + c * p.1 + e, b * p.0 + d * p.1 + f)
+    }
+
+    fn scale(&self) -> f64{
+        let [a, b, c, d, _, _] = self.0;
+        (a * d - b * c).abs().sqrt()
+    }
+
+    fn parse(source: &str) -> Result<Transform, String>{
+        let mut transform = Transform::identity();
+        let mut rest = source.trim();
+        while !rest.is_empty(){
+            let open = rest.find('(').ok_or("Invalid transform")?;
+            let close = rest.find(')').ok_or("Invalid transform")?;
+            let name = rest[..open].trim_matches(|c: char| c.is_whitespace() || c == ',');
+            let mut tokens = PathTokens::new(&rest[open + 1..close]);
+            let mut args = Vec::<f64>::new();
+            while tokens.has_number(){
+                args.push(tokens.number()?);
+            }
+            let arg = |i: usize, default: f64| args.get(i).copied().unwrap_or(default);
+            let next = match (name, args.len()) {
+                ("matrix", 6) => Transform([args[0], args[1], args[2], args[3], args[4], args[5]]),
+                ("translate", 1..=2) => Transform([1.0, 0.0, 0.0, 1.0, args[0], arg(1, 0.0)]),
+                ("scale", 1..=2) => Transform([args[0], 0.0, 0.0, arg(1, args[0]), 0.0, 0.0]),
+                ("rotate", 1) | ("rotate", 3) => {
+                    let (sin, cos) = args[0].to_radians().sin_cos();
+                    let (cx, cy) = (arg(1, 0.0), arg(2, 0.0));
+                    Transform([cos, sin, -sin, cos, cx - cos * cx + sin * cy, cy - sin * cx - cos * cy])
+                },
+                ("skewX", 1) => Transform([1.0, 0.0, args[0].to_radians().tan(), 1.0, 0.0, 0.0]),
+                ("skewY", 1) => Transform([1.0, args[0].to_radians().tan(), 0.0, 1.0, 0.0, 0.0]),
+                _ => return Err(format!("Unsupported transform {}", name)),
+            };
+            //Transforms in a list apply right to left
+            transform = next.then(&transform);
+            rest = &rest[close + 1..];
+        }
+        Ok(transform)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Segment{
+    Line(Point),
+    Quadratic(Point, Point),
+    Cubic(Point, Point, Point),
+    Arc{ radii: Point, rotation: f64, large: bool, sweep: bool, end: Point },
+}
+
+struct Subpath{
+    start: Point,
+    segments: Vec<Segment>,
+    closed: bool,
+}
+
+impl Subpath{
+    //Samples the subpath in output coordinates, using roughly one point per step units
+    fn flatten(&self, transform: &Transform, step: f64, minimum: usize) -> Vec<Point>{
+        let mut output = vec![transform.apply(self.start)];
+        let mut current = self.start;
+        let scale = transform.scale();
+        let count = |length: f64| ((length * scale / step).ceil() as usize).clamp(minimum, 1024);
+
+        for segment in self.segments.iter(){
+            match *segment {
+                Segment::Line(end) => {
+                    output.push(transform.apply(end));
+                    current = end;
+                },
+                Segment::Quadratic(c, end) => {
+                    let n = count(distance(current, c) + distance(c, end));
+                    for i in 1..=n{
+                        let t = i as f64 / n as f64;
+                        let u = 1.0 - t;
+                        output.push(transform.apply((
+                            u * u * current.0 + 2.0 * u * t * c.0 + t * t * end.0,
+                            u * u * current.1 + 2.0 * u * t * c.1 + t * t * end.1)));
+                    }
+                    current = end;
+                },
+                Segment::Cubic(c1, c2, end) => {
+                    let n = count(distance(current, c1) + distance(c1, c2) + distance(c2, end));
+                    for i in 1..=n{
+                        let t = i as f64 / n as f64;
+                        let u = 1.0 - t;
+                        output.push(transform.apply((
+                            u * u * u * current.0 + 3.0 * u * u * t * c1.0 + 3.0 * u * t * t * c2.0 + t * t * t * end.0,
+                            u * u * u * current.1 + 3.0 * u * u * t * c1.1 + 3.0 * u * t * t * c2.1 + t * t * t * end.1)));
+                    }
+                    current = end;
+                },
+                Segment::Arc{ radii, rotation, large, sweep, end } => {
+                    match arc_center(current, radii, rotation, large, sweep, end) {
+                        Some((center, radii, start_angle, delta)) => {
+                            let n = count(delta.abs() * radii.0.max(radii.1));
+                            let (sin, cos) = rotation.to_radians().sin_cos();
+                            for i in 1..=n{
+                                let angle = start_angle + delta * i as f64 / n as f64;
+                                let (x, y) = (radii.0 * angle.cos(), radii.1 * angle.sin());
+                                output.push(transform.apply((center.0 + cos * x - sin * y, center.1 + sin * x + cos * y)));
+                            }
+                        },
+                        None => output.push(transform.apply(end)),
+                    }
+                    current = end;
+                },
+            }
+        }
+        output
+    }
+}
+
+struct Element{
+    subpaths: Vec<Subpath>,
+    transform: Transform,
+    fill: bool,
+    stroke: bool,
+}
+
+//Parses path, polygon, polyline, rect, circle and ellipse elements and rasterizes them onto the lattice
+pub fn parse(source: &str, mode: SvgMode, fit: ImageFit, xdim: isize, ydim: isize) -> Result<Blob, String>{
+    let mut elements = Vec::<Element>::new();
+    let mut group_transforms = vec![Transform::identity()];
+    let mut view_box: Option<[f64; 4]> = None;
+
+    for (name, attributes, self_closing) in Tags::new(source){
+        let attributes = attributes?;
+        let inherited = *group_transforms.last().unwrap();
+        let transform = match attributes.get("transform") {
+            Some(t) => Transform::parse(t)?.then(&inherited),
+            None => inherited,
+        };
+        match name {
+            "svg" if view_box.is_none() => view_box = parse_view_box(&attributes),
+            "g" if !self_closing => group_transforms.push(transform),
+            "/g" if group_transforms.len() > 1 => {
+                group_transforms.pop();
+            },
+            "path" | "polygon" | "polyline" | "rect" | "circle" | "ellipse" => {
+                let subpaths = element_subpaths(name, &attributes)?;
+                let fill = !matches!(presentation(&attributes, "fill").as_deref(), Some("none"));
+                let stroke = !matches!(presentation(&attributes, "stroke").as_deref(), None | Some("none"));
+                elements.push(Element{ subpaths, transform, fill, stroke });
+            },
+            _ => (),
+        }
+    }
+
+    if elements.is_empty(){
+        return Err("SVG contains no supported shapes".to_string());
+    }
+
+    let [min_x, min_y, width, height] = match view_box {
+        Some(v) => v,
+        None => geometry_bounds(&elements)?,
+    };
+    let (scale_x, scale_y) = match fit {
+        ImageFit::Stretch => (xdim as f64 / width, ydim as f64 / height),
+        ImageFit::Fit => {
+            let scale = (xdim as f64 / width).min(ydim as f64 / height);
+            (scale, scale)
+        }
+    };
+    let offset = ((xdim as f64 - width * scale_x)/2.0, (ydim as f64 - height * scale_y)/2.0);
+    let to_lattice = Transform([scale_x, 0.0, 0.0, scale_y, offset.0 - min_x * scale_x, offset.1 - min_y * scale_y]);
+
+    let mut mask = Mask::new_empty();
+    for element in elements.iter(){
+        let transform = element.transform.then(&to_lattice);
+        let rings: Vec<Vec<Point>> = element.subpaths.iter().map(|s| s.flatten(&transform, 1.0, 1)).collect();
+        let (fill, stroke) = match mode {
+            SvgMode::Outline => (false, true),
+            SvgMode::Fill => (true, true),
+            SvgMode::Auto => (element.fill, element.stroke || !element.fill),
+        };
+        if fill{
+            raster::fill(&rings, true, xdim, ydim, &mut mask);
+        }
+        if stroke{
+            for (ring, subpath) in rings.iter().zip(element.subpaths.iter()){
+                raster::stroke(ring, subpath.closed || fill, true, xdim, ydim, &mut mask);
+            }
+        }
+    }
+
+    Ok(Blob::new(mask.clip(xdim, ydim)))
+}
+
+fn parse_view_box(attributes: &HashMap<String, String>) -> Option<[f64; 4]>{
+    if let Some(v) = attributes.get("viewBox"){
+        let mut tokens = PathTokens::new(v);
+        let mut values = [0.0; 4];
+        for value in values.iter_mut(){
+            *value = tokens.number().ok()?;
+        }
+        return (values[2] > 0.0 && values[3] > 0.0).then_some(values);
+    }
+    let length = |name: &str| attributes.get(name).and_then(|v| PathTokens::new(v).number().ok());
+    match (length("width"), length("height")) {
+        (Some(w), Some(h)) if w > 0.0 && h > 0.0 => Some([0.0, 0.0, w, h]),
+        _ => None,
+    }
+}
+
+fn geometry_bounds(elements: &[Element]) -> Result<[f64; 4], String>{
+    let mut min = (f64::INFINITY, f64::INFINITY);
+    let mut max = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for element in elements{
+        for subpath in element.subpaths.iter(){
+            for p in subpath.flatten(&element.transform, f64::INFINITY, 32){
+                min = (min.0.min(p.0), min.1.min(p.1));
+                max = (max.0.max(p.0), max.1.max(p.1));
+            }
+        }
+    }
+    if !(max.0 > min.0 && max.1 > min.1){
+        return Err("SVG shapes have no area".to_string());
+    }
+    Ok([min.0, min.1, max.0 - min.0, max.1 - min.1])
+}
+
+//A presentation property such as fill, declarations in the style attribute win over the attribute itself
+fn presentation(attributes: &HashMap<String, String>, property: &str) -> Option<String>{
+    let declared = attributes.get("style").and_then(|style| style.rsplit(';')
+        .filter_map(|declaration| declaration.split_once(':'))
+        .find(|(name, _)| name.trim() == property)
+        .map(|(_, value)| value.trim().to_string()));
+    declared.or_else(|| attributes.get(property).map(|v| v.trim().to_string()))
+}
+
+fn element_subpaths(name: &str, attributes: &HashMap<String, String>) -> Result<Vec<Subpath>, String>{
+    let number = |key: &str| -> Result<f64, String> {
+        match attributes.get(key) {
+            Some(v) => PathTokens::new(v).number(),
+            None => Ok(0.0),
+        }
+    };
+    match name {
+        "path" => parse_path(attributes.get("d").map(|d| d.as_str()).unwrap_or("")),
+        "polygon" | "polyline" => {
+            let mut tokens = PathTokens::new(attributes.get("points").map(|p| p.as_str()).unwrap_or(""));
+            let mut vertices = Vec::<Point>::new();
+            while tokens.has_number(){
+                vertices.push((tokens.number()?, tokens.number()?));
+            }
+            if vertices.is_empty(){
+                return Ok(vec![]);
+            }
+            Ok(vec![Subpath{
+                start: vertices[0],
+                segments: vertices[1..].iter().map(|v| Segment::Line(*v)).collect(),
+                closed: name == "polygon",
+            }])
+        },
+        "rect" => {
+            let (x, y, w, h) = (number("x")?, number("y")?, number("width")?, number("height")?);
+            //Rects without a positive width and height aren't rendered
+            if !(w.is_finite() && h.is_finite() && w > 0.0 && h > 0.0){
+                return Ok(vec![]);
+            }
+            let (mut rx, mut ry) = (number("rx")?, number("ry")?);
+            if attributes.get("ry").is_none(){
+                ry = rx;
+            }
+            if attributes.get("rx").is_none(){
+                rx = ry;
+            }
+            let (rx, ry) = (rx.max(0.0).min(w/2.0), ry.max(0.0).min(h/2.0));
+            let corner = |end: Point| Segment::Arc{ radii: (rx, ry), rotation: 0.0, large: false, sweep: true, end };
+            let segments = if rx > 0.0 && ry > 0.0 {
+                vec![
+                    Segment::Line((x + w - rx, y)), corner((x + w, y + ry)),
+                    Segment::Line((x + w, y + h - ry)), corner((x + w - rx, y + h)),
+                    Segment::Line((x + rx, y + h)), corner((x, y + h - ry)),
+                    Segment::Line((x, y + ry)), corner((x + rx, y)),
+                ]
+            } else {
+                vec![Segment::Line((x + w, y)), Segment::Line((x + w, y + h)), Segment::Line((x, y + h))]
+            };
+            Ok(vec![Subpath{ start: (x + rx, y), segments, closed: true }])
+        },
+        "circle" | "ellipse" => {
+            let (cx, cy) = (number("cx")?, number("cy")?);
+            let radii = if name == "circle" { (number("r")?, number("r")?) } else { (number("rx")?, number("ry")?) };
+            if !(radii.0.is_finite() && radii.1.is_finite() && radii.0 > 0.0 && radii.1 > 0.0){
+                return Ok(vec![]);
+            }
+            let half = |end: Point| Segment::Arc{ radii, rotation: 0.0, large: false, sweep: true, end };
+            Ok(vec![Subpath{
+                start: (cx + radii.0, cy),
+                segments: vec![half((cx - radii.0, cy)), half((cx + radii.0, cy))],
+                closed: true,
+            }])
+        },
+        _ => Ok(vec![]),
+    }
+}
+
+fn parse_path(d: &str) -> Result<Vec<Subpath>, String>{
+    let mut tokens = PathTokens::new(d);
+    let mut subpaths = Vec::<Subpath>::new();
+    let mut current: Point = (0.0, 0.0);
+    let mut start: Point = (0.0, 0.0);
+    //Reflected control point for smooth curve commands
+    let mut last_control: Option<(u8, Point)> = None;
+    let mut command = 0_u8;
+
+    let push = |subpaths: &mut Vec<Subpath>, start: Point, segment: Segment| {
+        if subpaths.last().map(|s| s.closed).unwrap_or(true){
+            subpaths.push(Subpath{ start, segments: vec![], closed: false });
+        }
+        subpaths.last_mut().unwrap().segments.push(segment);
+    };
+
+    loop{
+        if let Some(c) = tokens.command(){
+            command = c;
+        } else if !tokens.has_number(){
+            break;
+        } else if command == 0 || command.eq_ignore_ascii_case(&b'Z'){
+            return Err("Path data has a number without a command".to_string());
+        }
+        let relative = command.is_ascii_lowercase();
+        let base = if relative { current } else { (0.0, 0.0) };
+        let point = |tokens: &mut PathTokens| -> Result<Point, String> {
+            Ok((tokens.number()? + base.0, tokens.number()? + base.1))
+        };
+
+        match command.to_ascii_uppercase() {
+            b'M' => {
+                current = point(&mut tokens)?;
+                start = current;
+                subpaths.push(Subpath{ start, segments: vec![], closed: false });
+                //Extra coordinate pairs after a move are implicit line commands
+                command = if relative { b'l' } else { b'L' };
+                last_control = None;
+            },
+            b'L' => {
+                current = point(&mut tokens)?;
+                push(&mut subpaths, start, Segment::Line(current));
+                last_control = None;
+            },
+            b'H' => {
+                current = (tokens.number()? + base.0, current.1);
+                push(&mut subpaths, start, Segment::Line(current));
+                last_control = None;
+            },
+            b'V' => {
+                current = (current.0, tokens.number()? + base.1);
+                push(&mut subpaths, start, Segment::Line(current));
+                last_control = None;
+            },
+            b'C' | b'S' => {
+                let c1 = if command.eq_ignore_ascii_case(&b'C') {
+                    point(&mut tokens)?
+                } else {
+                    reflect(last_control, b'C', current)
+                };
+                let c2 = point(&mut tokens)?;
+                let end = point(&mut tokens)?;
+                push(&mut subpaths, start, Segment::Cubic(c1, c2, end));
+                last_control = Some((b'C', c2));
+                current = end;
+            },
+            b'Q' | b'T' => {
+                let c = if command.eq_ignore_ascii_case(&b'Q') {
+                    point(&mut tokens)?
+                } else {
+                    reflect(last_control, b'Q', current)
+                };
+                let end = point(&mut tokens)?;
+                push(&mut subpaths, start, Segment::Quadratic(c, end));
+                last_control = Some((b'Q', c));
+                current = end;
+            },
+            b'A' => {
+                let radii = (tokens.number()?.abs(), tokens.number()?.abs());
+                let rotation = tokens.number()?;
+                let large = tokens.flag()?;
+                let sweep = tokens.flag()?;
+                let end = point(&mut tokens)?;
+                push(&mut subpaths, start, Segment::Arc{ radii, rotation, large, sweep, end });
+                last_control = None;
+                current = end;
+            },
+            b'Z' => {
+                if let Some(s) = subpaths.last_mut(){
+                    s.closed = true;
+                }
+                current = start;
+                last_control = None;
+            },
+            _ => return Err(format!("Unsupported path command {}", command as char)),
+        }
+    }
+    subpaths.retain(|s| !s.segments.is_empty());
+    Ok(subpaths)
+}
+
+fn reflect(last_control: Option<(u8, Point)>, kind: u8, current: Point) -> Point{
+    match last_control {
+        Some((k, c)) if k == kind => (2.0 * current.0 - c.0, 2.0 * current.1 - c.1),
+        _ => current,
+    }
+}
+
+fn distance(a: Point, b: Point) -> f64{
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+//Endpoint to center conversion from the SVG implementation notes (F.6.5)
+fn arc_center(start: Point, radii: Point, rotation: f64, large: bool, sweep: bool, end: Point) -> Option<(Point, Point, f64, f64)>{
+    let (mut rx, mut ry) = radii;
+    if rx == 0.0 || ry == 0.0 || start == end{
+        return None;
+    }
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let dx = (start.0 - end.0)/2.0;
+    let dy = (start.1 - end.1)/2.0;
+    let x1 = cos * dx + sin * dy;
+    let y1 = -sin * dx + cos * dy;
+
+    let lambda = (x1 * x1)/(rx * rx) + (y1 * y1)/(ry * ry);
+    if lambda > 1.0{
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+
+    let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let mut coefficient = (numerator / denominator).max(0.0).sqrt();
+    if large == sweep{
+        coefficient = -coefficient;
+    }
+    let cx1 = coefficient * rx * y1 / ry;
+    let cy1 = -coefficient * ry * x1 / rx;
+    let center = (
+        cos * cx1 - sin * cy1 + (start.0 + end.0)/2.0,
+        sin * cx1 + cos * cy1 + (start.1 + end.1)/2.0,
+    );
+
+    let angle = |u: Point, v: Point| {
+        let a = (u.0 * v.1 - u.1 * v.0).atan2(u.0 * v.0 + u.1 * v.1);
+        if a.is_nan() { 0.0 } else { a }
+    };
+    let u = ((x1 - cx1)/rx, (y1 - cy1)/ry);
+    let v = ((-x1 - cx1)/rx, (-y1 - cy1)/ry);
+    let start_angle = angle((1.0, 0.0), u);
+    let mut delta = angle(u, v) % (2.0 * PI);
+    if !sweep && delta > 0.0{
+        delta -= 2.0 * PI;
+    } else if sweep && delta < 0.0{
+        delta += 2.0 * PI;
+    }
+    Some((center, (rx, ry), start_angle, delta))
+}
+
+struct PathTokens<'a>{
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PathTokens<'a>{
+    fn new(source: &'a str) -> PathTokens<'a>{
+        PathTokens{ bytes: source.as_bytes(), position: 0 }
+    }
+
+    fn skip_separators(&mut self){
+        while self.position < self.bytes.len() && (self.bytes[self.position].is_ascii_whitespace() || self.bytes[self.position] == b','){
+            self.position += 1;
+        }
+    }
+
+    fn command(&mut self) -> Option<u8>{
+        self.skip_separators();
+        match self.bytes.get(self.position) {
+            Some(c) if c.is_ascii_alphabetic() && *c != b'e' && *c != b'E' => {
+                self.position += 1;
+                Some(*c)
+            },
+            _ => None,
+        }
+    }
+
+    fn has_number(&mut self) -> bool{
+        self.skip_separators();
+        matches!(self.bytes.get(self.position), Some(c) if c.is_ascii_digit() || *c == b'-' || *c == b'+' || *c == b'.')
+    }
+
+    fn number(&mut self) -> Result<f64, String>{
+        self.skip_separators();
+        let start = self.position;
+        let mut seen_dot = false;
+        let mut seen_exponent = false;
+        while let Some(c) = self.bytes.get(self.position){
+            let previous = if self.position > start { self.bytes[self.position - 1] } else { 0 };
+            let accept = match c {
+                b'0'..=b'9' => true,
+                b'-' | b'+' => self.position == start || previous == b'e' || previous == b'E',
+                b'.' if !seen_dot && !seen_exponent => {
+                    seen_dot = true;
+                    true
+                },
+                b'e' | b'E' if !seen_exponent && self.position > start => {
+                    seen_exponent = true;
+                    true
+                },
+                _ => false,
+            };
+            if !accept{
+                break;
+            }
+            self.position += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.position])
+            .ok()
+            .and_then(|t| t.parse::<f64>().ok())
+            .ok_or_else(|| format!("Expected a number at position {}", start))
+    }
+
+    //Arc flags may be written without separators, e.g. "a1 1 0 01 1 1"
+    fn flag(&mut self) -> Result<bool, String>{
+        self.skip_separators();
+        match self.bytes.get(self.position) {
+            Some(b'0') => { self.position += 1; Ok(false) },
+            Some(b'1') => { self.position += 1; Ok(true) },
+            _ => Err(format!("Expected an arc flag at position {}", self.position)),
+        }
+    }
+}
+
+//Iterates over the tags of an XML document as (name, attributes, self closing)
+struct Tags<'a>{
+    source: &'a str,
+    position: usize,
+}
+
+impl<'a> Tags<'a>{
+    fn new(source: &'a str) -> Tags<'a>{
+        Tags{ source, position: 0 }
+    }
+
+    fn attributes(body: &str) -> Result<HashMap<String, String>, String>{
+        let mut attributes = HashMap::<String, String>::new();
+        let mut rest = body.trim();
+        while let Some(equals) = rest.find('='){
+            let key = rest[..equals].trim().to_string();
+            let value_start = rest[equals + 1..].trim_start();
+            let quote = value_start.chars().next().ok_or("Attribute is missing a value")?;
+            if quote != '"' && quote != '\''{
+                return Err(format!("Attribute {} is not quoted", key));
+            }
+            let end = value_start[1..].find(quote).ok_or("Attribute value is not terminated")?;
+            attributes.insert(key, value_start[1..end + 1].to_string());
+            rest = value_start[end + 2..].trim_start();
+        }
+        Ok(attributes)
+    }
+}
+
+impl<'a> Iterator for Tags<'a>{
+    type Item = (&'a str, Result<HashMap<String, String>, String>, bool);
+
+    fn next(&mut self) -> Option<Self::Item>{
+        loop{
+            let open = self.position + self.source[self.position..].find('<')?;
+            let rest = &self.source[open..];
+            //Skip comments, declarations and processing instructions
+            let skip_to = if rest.starts_with("<!--") {
+                Some("-->")
+            } else if rest.starts_with("<!") || rest.starts_with("<?") {
+                Some(">")
+            } else {
+                None
+            };
+            if let Some(terminator) = skip_to{
+                self.position = open + rest.find(terminator)? + terminator.len();
+                continue;
+            }
+            let close = open + rest.find('>')?;
+            self.position = close + 1;
+            let tag = &self.source[open + 1..close];
+            let self_closing = tag.ends_with('/');
+            let tag = tag.trim_end_matches('/');
+            let name_end = tag.find(|c: char| c.is_whitespace()).unwrap_or(tag.len());
+            return Some((&tag[..name_end], Self::attributes(&tag[name_end..]), self_closing));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::barrier_shapes::Shape;
+
+    fn cells(source: &str, mode: SvgMode) -> Vec<(isize, isize, bool)>{
+        let blob = parse(source, mode, ImageFit::Stretch, 10, 10).unwrap();
+        blob.get_mask().points().collect()
+    }
+
+    fn svg(body: &str) -> String{
+        format!(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10">{}</svg>"#, body)
+    }
+
+    #[test]
+    fn rect_fill_and_outline(){
+        let rect = svg(r#"<rect x="2" y="2" width="6" height="6"/>"#);
+        let filled = parse(&rect, SvgMode::Fill, ImageFit::Stretch, 10, 10).unwrap();
+        assert_eq!(filled.get_mask().get((5, 5)), Some(true));
+        let outline = parse(&rect, SvgMode::Outline, ImageFit::Stretch, 10, 10).unwrap();
+        assert_eq!(outline.get_mask().get((5, 5)), None);
+        assert_eq!(outline.get_mask().get((2, 5)), Some(true));
+    }
+
+    #[test]
+    fn path_matches_rect(){
+        let rect = cells(&svg(r#"<rect x="1" y="1" width="8" height="8"/>"#), SvgMode::Fill);
+        let path = cells(&svg(r#"<path d="M1 1 h8 v8 H1 z"/>"#), SvgMode::Fill);
+        assert_eq!(rect, path);
+        let relative = cells(&svg(r#"<path d="m1,1 8,0 0,8 -8,0Z"/>"#), SvgMode::Fill);
+        assert_eq!(rect, relative);
+    }
+
+    #[test]
+    fn style_overrides_fill_attribute(){
+        let unfilled = parse(&svg(r#"<rect x="2" y="2" width="6" height="6" fill="black" style="stroke: red; fill: none"/>"#),
+            SvgMode::Auto, ImageFit::Stretch, 10, 10).unwrap();
+        assert_eq!(unfilled.get_mask().get((5, 5)), None);
+        let filled = parse(&svg(r#"<rect x="2" y="2" width="6" height="6" fill="none" style="fill:#000"/>"#),
+            SvgMode::Auto, ImageFit::Stretch, 10, 10).unwrap();
+        assert_eq!(filled.get_mask().get((5, 5)), Some(true));
+    }
+
+    #[test]
+    fn shapes_without_size_are_skipped(){
+        let expected = cells(&svg(r#"<circle cx="5" cy="5" r="3"/>"#), SvgMode::Fill);
+        let source = svg(r#"<rect x="1" y="1" width="-4" height="3"/><rect width="1e999" height="2"/><ellipse rx="0" ry="2"/><circle cx="5" cy="5" r="3"/>"#);
+        assert_eq!(cells(&source, SvgMode::Fill), expected);
+    }
+
+    #[test]
+    fn group_transforms_apply_to_children(){
+        let moved = cells(&svg(r#"<g transform="translate(4 0)"><rect x="0" y="0" width="2" height="2"/></g><rect x="9" y="9" width="1" height="1"/>"#), SvgMode::Fill);
+        assert!(moved.contains(&(4, 0, true)) && !moved.contains(&(0, 0, true)));
+    }
+
+    #[test]
+    fn unsupported_documents_are_errors(){
+        assert!(parse(&svg("<text>hi</text>"), SvgMode::Fill, ImageFit::Fit, 10, 10).is_err());
+        assert!(parse(&svg(r#"<rect width="2" height="2" transform="warp(3)"/>"#), SvgMode::Fill, ImageFit::Fit, 10, 10).is_err());
+    }
+}
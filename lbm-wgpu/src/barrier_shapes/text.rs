@@ -0,0 +1,159 @@
+use super::{blob::Blob, mask::Mask, raster};
+
+//Single stroke font on a grid 4 units wide, the cap height runs from y = 0 down to the baseline at y = 6
+//and descenders reach y = 8. Each glyph is a list of strokes separated by '|', each point is an "xy" pair
+const GLYPHS: [(char, &str); 94] = [
+    ('!', "20 24|26"),
+    ('"', "10 11|30 31"),
+    ('#', "10 16|30 36|02 42|04 44"),
+    ('$', "41 30 10 01 02 13 33 44 45 36 16 05|20 26"),
+    ('%', "00 11|35 46|40 06"),
+    ('&', "46 02 01 10 20 31 32 04 05 16 26 44"),
+    ('\'', "20 21"),
+    ('(', "30 21 25 36"),
+    (')', "10 21 25 16"),
+    ('*', "21 25|02 44|42 04"),
+    ('+', "21 25|03 43"),
+    (',', "26 27 18"),
+    ('-', "03 43"),
+    ('.', "26"),
+    ('/', "40 06"),
+    ('0', "10 30 41 45 36 16 05 01 10|05 41"),
+    ('1', "11 20 26|16 36"),
+    ('2', "01 10 30 41 42 06 46"),
+    ('3', "01 10 30 41 42 33 13|33 44 45 36 16 05"),
+    ('4', "36 30 04 44"),
+    ('5', "40 00 02 32 43 45 36 06"),
+    ('6', "41 30 10 01 05 16 36 45 43 32 12 03"),
+    ('7', "00 40 26"),
+    ('8', "10 30 41 42 33 13 02 01 10|13 04 05 16 36 45 44 33"),
+    ('9', "42 33 13 02 01 10 30 41 45 36 16 05"),
+    (':', "22|25"),
+    (';', "22|25 26 17"),
+    ('<', "41 03 45"),
+    ('=', "02 42|04 44"),
+    ('>', "01 43 05"),
+    ('?', "01 10 30 41 42 23 24|26"),
+    ('@', "33 23 14 15 26 36 43 41 30 10 01 05 16 46"),
+    ('A', "06 02 20 42 46|04 44"),
+    ('B', "06 00 30 41 42 33 03|33 44 45 36 06"),
+    ('C', "41 30 10 01 05 16 36 45"),
+    ('D', "00 30 41 45 36 06 00"),
+    ('E', "40 00 06 46|03 33"),
+    ('F', "40 00 06|03 33"),
+    ('G', "41 30 10 01 05 16 36 45 43 23"),
+    ('H', "00 06|40 46|03 43"),
+    ('I', "10 30|20 26|16 36"),
+    ('J', "10 40|30 35 26 16 05"),
+    ('K', "00 06|40 03|14 46"),
+    ('L', "00 06 46"),
+    ('M', "06 00 23 40 46"),
+    ('N', "06 00 46 40"),
+    ('O', "10 30 41 45 36 16 05 01 10"),
+    ('P', "06 00 30 41 42 33 03"),
+    ('Q', "10 30 41 45 36 16 05 01 10|24 46"),
+    ('R', "06 00 30 41 42 33 03|23 46"),
+    ('S', "41 30 10 01 02 13 33 44 45 36 16 05"),
+    ('T', "00 40|20 26"),
+    ('U', "00 05 16 36 45 40"),
+    ('V', "00 26 40"),
+    ('W', "00 16 23 36 40"),
+    ('X', "00 46|40 06"),
+    ('Y', "00 23 40|23 26"),
+    ('Z', "00 40 06 46"),
+    ('[', "30 10 16 36"),
+    ('\\', "00 46"),
+    (']', "10 30 36 16"),
+    ('^', "12 20 32"),
+    ('_', "07 47"),
+    ('`', "10 21"),
+    ('a', "12 32 43 46|44 14 05 16 36 45"),
+    ('b', "00 06|03 12 32 43 45 36 16 05"),
+    ('c', "43 32 12 03 05 16 36 45"),
+    ('d', "40 46|43 32 12 03 05 16 36 45"),
+    ('e', "04 44 43 32 12 03 05 16 36 45"),
+    ('f', "40 30 21 26|12 32"),
+    ('g', "43 32 12 03 04 15 35 44|42 47 38 18 07"),
+    ('h', "00 06|03 12 32 43 46"),
+    ('i', "20|22 26"),
+    ('j', "30|32 37 28 18 07"),
+    ('k', "00 06|42 04 46"),
+    ('l', "10 20 25 36"),
+    ('m', "02 06|03 12 23 26|23 32 43 46"),
+    ('n', "02 06|03 12 32 43 46"),
+    ('o', "12 32 43 45 36 16 05 03 12"),
+    ('p', "02 08|03 12 32 43 45 36 16 05"),
+    ('q', "42 48|43 32 12 03 05 16 36 45"),
+    ('r', "02 06|04 22 42"),
+    ('s', "42 12 03 14 34 45 36 06"),
+    ('t', "20 25 36|12 32"),
+    ('u', "02 05 16 36 45|42 46"),
+    ('v', "02 26 42"),
+    ('w', "02 16 24 36 42"),
+    ('x', "02 46|42 06"),
+    ('y', "02 04 15 35 44|42 47 38 18 07"),
+    ('z', "02 42 06 46"),
+    ('{', "30 21 22 13 24 25 36"),
+    ('|', "20 26"),
+    ('}', "10 21 22 33 24 25 16"),
+    ('~', "03 12 34 43"),
+];
+
+const CAP_HEIGHT: f64 = 6.0;
+const ADVANCE: f64 = 6.0;
+const LINE_HEIGHT: f64 = 10.0;
+
+//Width and height in lattice cells of the text at the given cap height, before rotation
+pub fn measure(text: &str, size: f64) -> (f64, f64){
+    let unit = size / CAP_HEIGHT;
+    let lines: Vec<usize> = text.lines().map(|l| l.chars().count()).collect();
+    let longest = lines.iter().copied().max().unwrap_or(0);
+    let width = if longest == 0 { 0.0 } else { (longest as f64 * ADVANCE - 2.0) * unit };
+    let height = if lines.is_empty() { 0.0 } else { ((lines.len() - 1) as f64 * LINE_HEIGHT + CAP_HEIGHT) * unit };
+    (width, height)
+}
+
+//Renders text with the top left corner of its first line at position, size is the cap height in cells,
+//rotation is in degrees clockwise about that corner and thickness is the pen width in cells
+pub fn render(
+    text: &str,
+    size: f64,
+    position: (f64, f64),
+    rotation: f64,
+    thickness: f64,
+    xdim: isize,
+    ydim: isize) -> Blob{
+
+        let unit = size / CAP_HEIGHT;
+        let (sin, cos) = rotation.to_radians().sin_cos();
+        let place = |x: f64, y: f64| (position.0 + (x * cos - y * sin) * unit, position.1 + (x * sin + y * cos) * unit);
+
+        let mut mask = Mask::new_empty();
+        for (row, line) in text.lines().enumerate(){
+            for (column, character) in line.chars().enumerate(){
+                let origin = (column as f64 * ADVANCE, row as f64 * LINE_HEIGHT);
+                for stroke in glyph(character).split('|').filter(|s| !s.is_empty()){
+                    let path: Vec<(f64, f64)> = stroke.split_whitespace()
+                        .filter_map(|p| {
+                            let mut digits = p.chars().filter_map(|c| c.to_digit(10));
+                            Some(place(origin.0 + digits.next()? as f64, origin.1 + digits.next()? as f64))
+                        })
+                        .collect();
+                    raster::thick_stroke(&path, false, thickness, true, xdim, ydim, &mut mask);
+                }
+            }
+        }
+        Blob::new(mask.clip(xdim, ydim))
+}
+
+//Characters without a glyph are drawn as '?', spaces have no strokes
+fn glyph(character: char) -> &'static str{
+    if character.is_whitespace(){
+        return "";
+    }
+    GLYPHS.iter()
+        .find(|g| g.0 == character)
+        .or_else(|| GLYPHS.iter().find(|g| g.0 == '?'))
+        .map(|g| g.1)
+        .unwrap_or("")
+}
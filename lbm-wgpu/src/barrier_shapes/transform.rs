@@ -0,0 +1,180 @@
+use wasm_bindgen::prelude::*;
+
+use super::{Shape, blob::Blob, mask::Mask};
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum MirrorAxis{
+    //Flips left to right across the vertical line x = position
+    Vertical,
+    //Flips top to bottom across the horizontal line y = position
+    Horizontal,
+}
+
+//Centerlines of the lattice that drawing is mirrored across, Horizontal mirrors top to bottom
+#[wasm_bindgen]
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Symmetry{
+    Off,
+    Horizontal,
+    Vertical,
+    Both,
+}
+
+impl Symmetry{
+    //The cell and its mirror images, a cell on a centerline is its own image
+    pub fn images(&self, cell: (isize, isize), xdim: isize, ydim: isize) -> Vec<(isize, isize)>{
+        let (flipped_x, flipped_y) = (xdim - 1 - cell.0, ydim - 1 - cell.1);
+        match self {
+            Symmetry::Off => vec![cell],
+            Symmetry::Horizontal => vec![cell, (cell.0, flipped_y)],
+            Symmetry::Vertical => vec![cell, (flipped_x, cell.1)],
+            Symmetry::Both => vec![cell, (cell.0, flipped_y), (flipped_x, cell.1), (flipped_x, flipped_y)],
+        }
+    }
+}
+
+//Maps a cell (x, y) to (a*x + b*y + tx, c*x + d*y + ty), cell centers sit on integer coordinates
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Affine{
+    pub matrix: [[f64; 2]; 2],
+    pub offset: (f64, f64),
+}
+
+impl Affine{
+
+    pub fn identity() -> Affine{
+        Affine{
+            matrix: [[1.0, 0.0], [0.0, 1.0]],
+            offset: (0.0, 0.0),
+        }
+    }
+
+    pub fn translation(dx: f64, dy: f64) -> Affine{
+        Affine{
+            offset: (dx, dy),
+            ..Self::identity()
+        }
+    }
+
+    //Degrees clockwise on screen, matching the rotated primitives
+    pub fn rotation(degrees: f64, pivot: (f64, f64)) -> Affine{
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Self::about(pivot, [[cos, -sin], [sin, cos]])
+    }
+
+    pub fn scale(factor: (f64, f64), pivot: (f64, f64)) -> Affine{
+        Self::about(pivot, [[factor.0, 0.0], [0.0, factor.1]])
+    }
+
+    pub fn mirror(axis: MirrorAxis, position: f64) -> Affine{
+        match axis {
+            MirrorAxis::Vertical => Self::about((position, 0.0), [[-1.0, 0.0], [0.0, 1.0]]),
+            MirrorAxis::Horizontal => Self::about((0.0, position), [[1.0, 0.0], [0.0, -1.0]]),
+        }
+    }
+
+    //Applies self first and then next
+    pub fn then(&self, next: &Affine) -> Affine{
+        let (a, b) = (next.matrix, self.matrix);
+        let mut matrix = [[0.0; 2]; 2];
+        for (i, row) in matrix.iter_mut().enumerate(){
+            for (j, value) in row.iter_mut().enumerate(){
+                *value = a[i][0] * b[0][j] + a[i][1] * b[1][j];
+            }
+        }
+        Affine{
+            matrix,
+            offset: next.apply(self.offset),
+        }
+    }
+
+    pub fn apply(&self, point: (f64, f64)) -> (f64, f64){
+        let m = self.matrix;
+        (m[0][0] * point.0 + m[0][1] * point.1 + self.offset.0, m[1][0] * point.0 + m[1][1] * point.1 + self.offset.1)
+    }
+
+    pub fn inverse(&self) -> Option<Affine>{
+        let m = self.matrix;
+        let determinant = m[0][0] * m[1][1] - m[0][1] * m[1][0];
+        if determinant.abs() < 1e-12{
+            return None;
+        }
+        let matrix = [[m[1][1]/determinant, -m[0][1]/determinant], [-m[1][0]/determinant, m[0][0]/determinant]];
+        let linear = Affine{ matrix, offset: (0.0, 0.0) };
+        let offset = linear.apply(self.offset);
+        Some(Affine{ matrix, offset: (-offset.0, -offset.1) })
+    }
+
+    fn about(pivot: (f64, f64), matrix: [[f64; 2]; 2]) -> Affine{
+        Self::translation(-pivot.0, -pivot.1)
+            .then(&Affine{ matrix, offset: (0.0, 0.0) })
+            .then(&Self::translation(pivot.0, pivot.1))
+    }
+}
+
+//Re-rasterizes the shape under the transform, every destination cell whose center lands inside a source cell
+//takes that cell's value so rotations and enlargements don't leave holes between the moved points
+pub fn transform<S: Shape + ?Sized>(shape: &S, affine: &Affine, xdim: isize, ydim: isize) -> Blob{
+    let source = shape.get_mask();
+    let mut mask = Mask::new_empty();
+    let in_bounds = |x: isize, y: isize| x >= 0 && y >= 0 && x < xdim && y < ydim;
+
+    let inverse = match affine.inverse() {
+        Some(i) => i,
+        //Degenerate transforms collapse the shape, so only the forward mapped centers remain
+        None => {
+            for (x, y, value) in source.points(){
+                let (x, y) = affine.apply((x as f64, y as f64));
+                let (x, y) = (x.round() as isize, y.round() as isize);
+                if in_bounds(x, y){
+                    mask.set((x, y), value);
+                }
+            }
+            return Blob::new(mask);
+        }
+    };
+
+    //Everything lands inside the transformed source rectangle, so the destination is sized once
+    let (origin, width, height) = source.rectangle();
+    let (left, top) = (origin.0 as f64 - 0.5, origin.1 as f64 - 0.5);
+    let (right, bottom) = (left + width as f64, top + height as f64);
+    let corners = [(left, top), (right, top), (right, bottom), (left, bottom)].map(|c| affine.apply(c));
+    let min = (corners.iter().map(|c| c.0).fold(f64::INFINITY, f64::min).floor() as isize, corners.iter().map(|c| c.1).fold(f64::INFINITY, f64::min).floor() as isize);
+    let max = (corners.iter().map(|c| c.0).fold(f64::NEG_INFINITY, f64::max).ceil() as isize, corners.iter().map(|c| c.1).fold(f64::NEG_INFINITY, f64::max).ceil() as isize);
+    let (min, max) = ((min.0.max(0), min.1.max(0)), (max.0.min(xdim - 1), max.1.min(ydim - 1)));
+    mask.reserve(min, max);
+    let mut claimed = Mask::new_empty();
+    claimed.reserve(min, max);
+
+    for (cell_x, cell_y, value) in source.points(){
+        let (x, y) = (cell_x as f64, cell_y as f64);
+        let corners = [(x - 0.5, y - 0.5), (x + 0.5, y - 0.5), (x + 0.5, y + 0.5), (x - 0.5, y + 0.5)].map(|c| affine.apply(c));
+        let min_x = corners.iter().map(|c| c.0).fold(f64::INFINITY, f64::min).floor() as isize;
+        let max_x = corners.iter().map(|c| c.0).fold(f64::NEG_INFINITY, f64::max).ceil() as isize;
+        let min_y = corners.iter().map(|c| c.1).fold(f64::INFINITY, f64::min).floor() as isize;
+        let max_y = corners.iter().map(|c| c.1).fold(f64::NEG_INFINITY, f64::max).ceil() as isize;
+
+        let mut covered = false;
+        for dy in min_y.max(0)..=max_y.min(ydim - 1){
+            for dx in min_x.max(0)..=max_x.min(xdim - 1){
+                let back = inverse.apply((dx as f64, dy as f64));
+                if (back.0.round() as isize, back.1.round() as isize) == (cell_x, cell_y){
+                    mask.set((dx, dy), value);
+                    claimed.set((dx, dy), true);
+                    covered = true;
+                }
+            }
+        }
+
+        //Shrinking can leave a cell without any destination center inside it, keep thin features by
+        //falling back to the cell its center maps to unless another source cell already owns it
+        if !covered{
+            let center = affine.apply((x, y));
+            let (cx, cy) = (center.0.round() as isize, center.1.round() as isize);
+            if in_bounds(cx, cy) && claimed.get((cx, cy)).is_none() && mask.get((cx, cy)) != Some(!value){
+                mask.set((cx, cy), value);
+            }
+        }
+    }
+    Blob::new(mask)
+}
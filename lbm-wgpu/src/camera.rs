@@ -0,0 +1,59 @@
+//Zoomed in views are capped so a single cell never covers more than a fraction of the window
+const MAX_SCALE: f64 = 64.0;
+
+//Which part of the lattice fills the window. Offset is the lattice position, in cells, shown at the top left
+//corner and scale is how much larger cells are drawn than when the whole lattice fits the window. Window
+//positions are measured in unzoomed cells, the units the cursor is converted to
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Camera{
+    offset: (f64, f64),
+    scale: f64,
+    x: f64,
+    y: f64,
+}
+
+impl Camera{
+
+    pub fn new(x: u32, y: u32) -> Camera{
+        Camera{
+            offset: (0.0, 0.0),
+            scale: 1.0,
+            x: x as f64,
+            y: y as f64,
+        }
+    }
+
+    pub fn reset(&mut self){
+        self.offset = (0.0, 0.0);
+        self.scale = 1.0;
+    }
+
+    pub fn to_lattice(&self, window: (f64, f64)) -> (f64, f64){
+        (self.offset.0 + window.0/self.scale, self.offset.1 + window.1/self.scale)
+    }
+
+    //Zooms by factor keeping the lattice position under the window position where it is
+    pub fn zoom(&mut self, factor: f64, window: (f64, f64)){
+        let anchor = self.to_lattice(window);
+        self.scale = (self.scale * factor).clamp(1.0, MAX_SCALE);
+        self.offset = (anchor.0 - window.0/self.scale, anchor.1 - window.1/self.scale);
+        self.clamp();
+    }
+
+    //Drags the lattice along with the cursor
+    pub fn pan(&mut self, delta: (f64, f64)){
+        self.offset = (self.offset.0 - delta.0/self.scale, self.offset.1 - delta.1/self.scale);
+        self.clamp();
+    }
+
+    //Offset then scale, laid out as the Camera struct in render.wgsl and overlay.wgsl
+    pub fn uniform(&self) -> [f32; 4]{
+        [self.offset.0 as f32, self.offset.1 as f32, self.scale as f32, 0.0]
+    }
+
+    //Keeps the window inside the lattice
+    fn clamp(&mut self){
+        self.offset.0 = self.offset.0.clamp(0.0, self.x - self.x/self.scale);
+        self.offset.1 = self.offset.1.clamp(0.0, self.y - self.y/self.scale);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::lbm::ColorMap;
+
+//Entries in the lookup table lut.wgsl samples, the gradient between stops is resolved when the table is built
+pub const LUT_SIZE: usize = 256;
+
+//A color at a position from 0 (lowest value shown) to 1 (highest)
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct ColorStop{
+    pub position: f32,
+    pub color: [f32; 3],
+}
+
+//Colors in value order, evenly spaced. Inferno and Viridis run light to dark as the original shaders did
+const INFERNO: [u32; 5] = [0xfcffa4, 0xf88e0a, 0xbc3855, 0x58116f, 0x000005];
+const VIRIDIS: [u32; 5] = [0xfde726, 0x5ec962, 0x22908c, 0x3b538b, 0x440255];
+const JET: [u32; 9] = [0x000080, 0x0000ff, 0x0080ff, 0x00ffff, 0x80ff80, 0xffff00, 0xff8000, 0xff0000, 0x800000];
+const PLASMA: [u32; 11] = [0x0d0887, 0x41049d, 0x6a00a8, 0x8f0da4, 0xb12a90, 0xcc4778, 0xe16462, 0xf2844b, 0xfca636, 0xfcce25, 0xf0f921];
+const MAGMA: [u32; 11] = [0x000004, 0x140e36, 0x3b0f70, 0x641a80, 0x8c2981, 0xb73779, 0xde4968, 0xf7705c, 0xfe9f6d, 0xfecf92, 0xfcfdbf];
+const CIVIDIS: [u32; 11] = [0x00224e, 0x123570, 0x3b496c, 0x575d6d, 0x707173, 0x8a8678, 0xa59c74, 0xc3b369, 0xe1cc55, 0xfee838, 0xffea46];
+const TURBO: [u32; 15] = [0x30123b, 0x4145ab, 0x4675ed, 0x39a2fc, 0x1bcfd4, 0x24eca6, 0x61fc6c, 0xa4fc3b, 0xd1e834, 0xf3c63a, 0xfe9b2d, 0xf36315, 0xd93806, 0xb11901, 0x7a0402];
+const COOLWARM: [u32; 9] = [0x3b4cc0, 0x6282ea, 0x8db0fe, 0xb8d0f9, 0xdddcdc, 0xf5c4ad, 0xf49a7b, 0xe0654f, 0xb40426];
+const GRAYSCALE: [u32; 2] = [0x000000, 0xffffff];
+//Paul Tol's sunset, a diverging map that stays distinguishable under the common forms of color blindness
+const SUNSET: [u32; 11] = [0x364b9a, 0x4a7bb7, 0x6ea6cd, 0x98cae1, 0xc2e4ef, 0xeaeccc, 0xfeda8b, 0xfdb366, 0xf67e4b, 0xdd3d2d, 0xa50026];
+
+//Stops of a built in map, None for Custom whose stops come from the user
+pub fn stops(color_map: ColorMap) -> Option<Vec<ColorStop>>{
+    let colors: &[u32] = match color_map {
+        ColorMap::Inferno => &INFERNO,
+        ColorMap::Viridis => &VIRIDIS,
+        ColorMap::Jet => &JET,
+        ColorMap::Plasma => &PLASMA,
+        ColorMap::Magma => &MAGMA,
+        ColorMap::Cividis => &CIVIDIS,
+        ColorMap::Turbo => &TURBO,
+        ColorMap::Coolwarm => &COOLWARM,
+        ColorMap::Grayscale => &GRAYSCALE,
+        ColorMap::Sunset => &SUNSET,
+        ColorMap::Custom => return None,
+    };
+    let last = (colors.len() - 1) as f32;
+    Some(colors.iter().enumerate().map(|(i, c)| ColorStop{
+        position: i as f32/last,
+        color: [(c >> 16 & 0xff) as f32/255.0, (c >> 8 & 0xff) as f32/255.0, (c & 0xff) as f32/255.0],
+    }).collect())
+}
+
+//Values shown from the first to the last color. Jet keeps its wider range from before the lookup tables
+pub fn default_range(color_map: ColorMap) -> (f32, f32){
+    match color_map {
+        ColorMap::Jet => (-0.2, 0.2),
+        _ => (-2.0/15.0, 2.0/15.0),
+    }
+}
+
+//Reads stops given as position, red, green, blue quadruples with every value from 0 to 1 and positions
+//increasing, as uploaded from the frontend
+pub fn parse_stops(values: &[f32]) -> Result<Vec<ColorStop>, String>{
+    if !values.chunks_exact(4).remainder().is_empty(){
+        return Err(format!("Expected position, red, green, blue for every stop but got {} values", values.len()));
+    }
+    if values.len() < 8{
+        return Err("A color map needs at least two stops".to_owned());
+    }
+    if let Some(v) = values.iter().find(|v| !(0.0..=1.0).contains(*v)){
+        return Err(format!("Color stop value {} is outside 0 to 1", v));
+    }
+    let stops: Vec<ColorStop> = values.chunks_exact(4).map(|s| ColorStop{ position: s[0], color: [s[1], s[2], s[3]] }).collect();
+    if stops.windows(2).any(|pair| pair[1].position < pair[0].position){
+        return Err("Color stop positions must not decrease".to_owned());
+    }
+    Ok(stops)
+}
+
+//Samples the gradient through the stops at LUT_SIZE evenly spaced positions, colors before the first stop or
+//after the last are held. Entries are padded to four floats to match the array<vec4<f32>> in lut.wgsl
+pub fn lut(stops: &[ColorStop]) -> Vec<[f32; 4]>{
+    (0..LUT_SIZE).map(|i| {
+        let t = i as f32/(LUT_SIZE - 1) as f32;
+        let after = stops.iter().position(|s| s.position >= t).unwrap_or(stops.len() - 1);
+        let color = if after == 0 || stops[after].position < t {
+            stops[after].color
+        } else {
+            let (a, b) = (stops[after - 1], stops[after]);
+            let weight = if b.position > a.position { (t - a.position)/(b.position - a.position) } else { 1.0 };
+            [0, 1, 2].map(|c| a.color[c] + weight * (b.color[c] - a.color[c]))
+        };
+        [color[0], color[1], color[2], 1.0]
+    }).collect()
+}
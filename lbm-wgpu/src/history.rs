@@ -0,0 +1,388 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::barrier_shapes::{Shape, blob::Blob, mask::Mask};
+
+//One committed shape stored as runs of consecutive cells along a row, with a bit per cell for the value it wrote
+//and a bit for the value it replaced. Filled shapes come down to a few runs per row, so this is far smaller
+//than keeping the shape's point set or every cell index around. Moving or deleting a shape records another
+//entry with the same id, so the newest entry for an id holds that shape's current cells
+struct Entry{
+    id: u32,
+    //(first cell index, length) in increasing order, the bits run through the runs' cells in the same order
+    runs: Vec<(u32, u32)>,
+    drawn: Vec<u64>,
+    previous: Vec<u64>,
+    //[min x, min y, max x, max y] of the drawn cells, None if the entry only erases
+    bounds: Option<[u32; 4]>,
+}
+
+//Undo and redo for shapes drawn onto the lattice. Cells no shape has touched keep the value of the base barrier
+//the history was last cleared with, so undoing never removes preset or scene walls
+pub struct History{
+    x: u32,
+    y: u32,
+    //Value of every cell as left by the base barrier and the recorded shapes, one bit per cell
+    state: Vec<u64>,
+    undo_stack: VecDeque<Entry>,
+    redo_stack: Vec<Entry>,
+    depth: usize,
+    next_id: u32,
+}
+
+impl History{
+
+    pub fn new(x: u32, y: u32, depth: usize) -> History{
+        History{
+            x,
+            y,
+            state: vec![0; (x as usize * y as usize).div_ceil(64)],
+            undo_stack: VecDeque::<Entry>::new(),
+            redo_stack: Vec::<Entry>::new(),
+            depth: depth.max(1),
+            next_id: 0,
+        }
+    }
+
+    //Forgets every shape, base holds what is on the lattice outside the history and every other cell is fluid
+    pub fn clear(&mut self, base: &Mask){
+        self.state.iter_mut().for_each(|b| *b = 0);
+        for p in base.points().filter(|p| p.2 && p.0 >= 0 && p.1 >= 0 && (p.0 as u32) < self.x && (p.1 as u32) < self.y){
+            set_bit(&mut self.state, p.0 as usize + p.1 as usize * self.x as usize, true);
+        }
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    //Older shapes past the depth are forgotten, their cells keep the value they were drawn with
+    pub fn set_depth(&mut self, depth: usize){
+        self.depth = depth.max(1);
+        while self.undo_stack.len() > self.depth{
+            self.undo_stack.pop_front();
+        }
+    }
+
+    //Records a newly drawn shape and returns its id, anything that could have been redone is dropped
+    pub fn push(&mut self, shape: &dyn Shape) -> u32{
+        let id = self.next_id;
+        self.next_id += 1;
+        let points: Vec<(u32, bool)> = shape.get_mask().points()
+            .filter(|p| p.0 >= 0 && p.1 >= 0 && (p.0 as u32) < self.x && (p.1 as u32) < self.y)
+            .map(|p| (p.0 as u32 + p.1 as u32 * self.x, p.2))
+            .collect();
+        self.record(id, points);
+        id
+    }
+
+    //Newest shape with a drawn cell within tolerance of location
+    pub fn pick(&self, location: (isize, isize), tolerance: isize) -> Option<u32>{
+        let mut seen = HashSet::<u32>::new();
+        for entry in self.undo_stack.iter().rev(){
+            if !seen.insert(entry.id){
+                continue;
+            }
+            let bounds = match entry.bounds {
+                Some(b) => b,
+                None => continue,
+            };
+            if location.0 < bounds[0] as isize - tolerance || location.0 > bounds[2] as isize + tolerance
+                || location.1 < bounds[1] as isize - tolerance || location.1 > bounds[3] as isize + tolerance{
+                continue;
+            }
+            let mut bit = 0;
+            for (start, length) in entry.runs.iter().map(|(s, l)| (*s as usize, *l as usize)){
+                let (x, y) = ((start % self.x as usize) as isize, (start / self.x as usize) as isize);
+                let (first, last) = ((location.0 - tolerance).max(x), (location.0 + tolerance).min(x + length as isize - 1));
+                if (y - location.1).abs() <= tolerance
+                    && (first..=last).any(|c| get_bit(&entry.drawn, bit + (c - x) as usize)){
+                    return Some(entry.id);
+                }
+                bit += length;
+            }
+        }
+        None
+    }
+
+    //Whether the cell at index (x + y * width) is solid as the history last left it
+    pub fn is_solid(&self, index: usize) -> bool{
+        get_bit(&self.state, index)
+    }
+
+    pub fn bounds(&self, id: u32) -> Option<[isize; 4]>{
+        self.current(id)?.bounds.map(|b| b.map(|v| v as isize))
+    }
+
+    //Cells the shape currently covers
+    pub fn cells(&self, id: u32) -> Option<Blob>{
+        let cells = self.drawn_cells(id)?;
+        Some(Blob::new(Mask::from_points(cells.iter().map(|c| ((c % self.x) as isize, (c / self.x) as isize, true)))))
+    }
+
+    //Erases the shape's cells and redraws them offset, returns the cells to draw
+    pub fn translate(&mut self, id: u32, offset: (isize, isize)) -> Option<Mask>{
+        let cells = self.drawn_cells(id)?;
+        let mut points: Vec<(u32, bool)> = cells.iter().map(|c| (*c, false)).collect();
+        points.extend(cells.iter()
+            .map(|c| ((c % self.x) as isize + offset.0, (c / self.x) as isize + offset.1))
+            .filter(|c| c.0 >= 0 && c.1 >= 0 && c.0 < self.x as isize && c.1 < self.y as isize)
+            .map(|c| (c.0 as u32 + c.1 as u32 * self.x, true)));
+        self.record(id, points);
+        self.undo_stack.back().map(|e| self.to_mask(e, &e.drawn))
+    }
+
+    //Erases the shape's cells, returns the cells to draw
+    pub fn delete(&mut self, id: u32) -> Option<Mask>{
+        let cells = self.drawn_cells(id)?;
+        self.record(id, cells.iter().map(|c| (*c, false)).collect());
+        self.undo_stack.back().map(|e| self.to_mask(e, &e.drawn))
+    }
+
+    fn current(&self, id: u32) -> Option<&Entry>{
+        self.undo_stack.iter().rev().find(|e| e.id == id).filter(|e| e.bounds.is_some())
+    }
+
+    fn drawn_cells(&self, id: u32) -> Option<Vec<u32>>{
+        let entry = self.current(id)?;
+        Some(entry.cells().enumerate().filter(|(i, _)| get_bit(&entry.drawn, *i)).map(|(_, c)| c).collect())
+    }
+
+    fn record(&mut self, id: u32, mut points: Vec<(u32, bool)>){
+        //Drawn sorts after erased, so a cell listed both ways ends up drawn
+        points.sort_unstable();
+        points.reverse();
+        points.dedup_by_key(|p| p.0);
+        points.reverse();
+        if points.is_empty(){
+            return;
+        }
+
+        let mut runs = Vec::<(u32, u32)>::new();
+        for (cell, _) in points.iter(){
+            match runs.last_mut() {
+                Some(run) if run.0 + run.1 == *cell && cell % self.x != 0 => run.1 += 1,
+                _ => runs.push((*cell, 1)),
+            }
+        }
+        let mut entry = Entry{
+            id,
+            runs,
+            drawn: vec![0; points.len().div_ceil(64)],
+            previous: vec![0; points.len().div_ceil(64)],
+            bounds: None,
+        };
+        for (i, (cell, value)) in points.iter().enumerate(){
+            set_bit(&mut entry.previous, i, get_bit(&self.state, *cell as usize));
+            set_bit(&mut entry.drawn, i, *value);
+            set_bit(&mut self.state, *cell as usize, *value);
+            if *value{
+                let (x, y) = (cell % self.x, cell / self.x);
+                entry.bounds = Some(match entry.bounds {
+                    Some(b) => [b[0].min(x), b[1].min(y), b[2].max(x), b[3].max(y)],
+                    None => [x, y, x, y],
+                });
+            }
+        }
+
+        self.redo_stack.clear();
+        self.push_undo(entry);
+    }
+
+    //Older entries past the depth are forgotten
+    fn push_undo(&mut self, entry: Entry){
+        self.undo_stack.push_back(entry);
+        if self.undo_stack.len() > self.depth{
+            self.undo_stack.pop_front();
+        }
+    }
+
+    //Returns the cells to redraw so the lattice matches its state before the last shape
+    pub fn undo(&mut self) -> Option<Mask>{
+        let entry = self.undo_stack.pop_back()?;
+        let blob = self.apply(&entry, false);
+        self.redo_stack.push(entry);
+        Some(blob)
+    }
+
+    pub fn redo(&mut self) -> Option<Mask>{
+        let entry = self.redo_stack.pop()?;
+        let blob = self.apply(&entry, true);
+        self.push_undo(entry);
+        Some(blob)
+    }
+
+    //Every solid cell as the history last left it, base barrier included
+    pub fn barrier(&self) -> Blob{
+        let mut mask = Mask::new_empty();
+        mask.reserve((0, 0), (self.x as isize - 1, self.y as isize - 1));
+        for i in (0..self.x as usize * self.y as usize).filter(|i| get_bit(&self.state, *i)){
+            mask.set(((i % self.x as usize) as isize, (i / self.x as usize) as isize), true);
+        }
+        Blob::new(mask)
+    }
+
+    fn apply(&mut self, entry: &Entry, forward: bool) -> Mask{
+        let values = if forward { &entry.drawn } else { &entry.previous };
+        for (i, cell) in entry.cells().enumerate(){
+            set_bit(&mut self.state, cell as usize, get_bit(values, i));
+        }
+        self.to_mask(entry, values)
+    }
+
+    fn to_mask(&self, entry: &Entry, values: &[u64]) -> Mask{
+        Mask::from_points(entry.cells().enumerate()
+            .map(|(i, cell)| ((cell % self.x) as isize, (cell / self.x) as isize, get_bit(values, i))))
+    }
+}
+
+impl Entry{
+    //Every cell the entry covers, in the order of its bits
+    fn cells(&self) -> impl Iterator<Item = u32> + Clone + '_{
+        self.runs.iter().flat_map(|(start, length)| *start..start + length)
+    }
+}
+
+fn get_bit(bits: &[u64], index: usize) -> bool{
+    bits[index / 64] >> (index % 64) & 1 == 1
+}
+
+fn set_bit(bits: &mut [u64], index: usize, value: bool){
+    if value{
+        bits[index / 64] |= 1 << (index % 64);
+    } else {
+        bits[index / 64] &= !(1 << (index % 64));
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn square(min: (isize, isize), size: isize, value: bool) -> Blob{
+        Blob::new(Mask::from_points((0..size * size).map(move |i| (min.0 + i % size, min.1 + i / size, value))))
+    }
+
+    fn solid(history: &History) -> Vec<(isize, isize)>{
+        history.barrier().get_mask().points().filter(|p| p.2).map(|p| (p.0, p.1)).collect()
+    }
+
+    #[test]
+    fn undo_and_redo_restore_the_cells(){
+        let mut history = History::new(10, 10, 10);
+        history.push(&square((1, 1), 3, true));
+        history.push(&square((2, 2), 3, false));
+        assert_eq!(solid(&history), vec![(1, 1), (2, 1), (3, 1), (1, 2), (1, 3)]);
+
+        let undone = history.undo().unwrap();
+        assert_eq!(undone.len(), 9);
+        assert_eq!(undone.get((2, 2)), Some(true));
+        assert_eq!(undone.get((4, 4)), Some(false));
+        assert_eq!(solid(&history).len(), 9);
+        history.undo();
+        assert!(solid(&history).is_empty());
+        assert!(history.undo().is_none());
+
+        history.redo();
+        history.redo();
+        assert_eq!(solid(&history).len(), 5);
+        assert!(history.redo().is_none());
+    }
+
+    #[test]
+    fn pushing_drops_the_redo_stack(){
+        let mut history = History::new(10, 10, 10);
+        history.push(&square((0, 0), 2, true));
+        history.undo();
+        history.push(&square((5, 5), 2, true));
+        assert!(history.redo().is_none());
+        assert_eq!(solid(&history), vec![(5, 5), (6, 5), (5, 6), (6, 6)]);
+    }
+
+    #[test]
+    fn undo_keeps_the_base_barrier(){
+        let mut history = History::new(8, 4, 10);
+        let walls = Mask::from_points((0..8).flat_map(|x| [(x, 0, true), (x, 3, true)]));
+        history.clear(&walls);
+        history.push(&square((2, 0), 2, false));
+        assert!(!history.is_solid(2));
+        history.undo();
+        assert!(history.is_solid(2) && history.is_solid(3));
+        assert_eq!(solid(&history).len(), 16);
+    }
+
+    #[test]
+    fn depth_limits_how_far_undo_goes(){
+        let mut history = History::new(10, 10, 2);
+        for i in 0..4{
+            history.push(&square((i * 2, 0), 1, true));
+        }
+        assert!(history.undo().is_some() && history.undo().is_some());
+        assert!(history.undo().is_none());
+        //Shapes past the depth keep their cells
+        assert_eq!(solid(&history), vec![(0, 0), (2, 0)]);
+        history.set_depth(1);
+        history.redo();
+        history.redo();
+        assert_eq!(history.undo_stack.len(), 1);
+    }
+
+    #[test]
+    fn entries_are_clipped_and_stored_as_runs(){
+        let mut history = History::new(10, 10, 10);
+        history.push(&square((-2, 8), 4, true));
+        assert_eq!(history.undo_stack[0].runs, vec![(80, 2), (90, 2)]);
+        history.push(&square((0, 0), 10, true));
+        assert_eq!(history.undo_stack[1].runs.len(), 10);
+    }
+
+    #[test]
+    fn pick_finds_the_newest_shape_near_a_point(){
+        let mut history = History::new(20, 20, 10);
+        let first = history.push(&square((2, 2), 4, true));
+        let second = history.push(&square((4, 4), 4, true));
+        let eraser = history.push(&square((12, 12), 4, false));
+        assert_eq!(history.pick((4, 4), 0), Some(second));
+        assert_eq!(history.pick((2, 2), 0), Some(first));
+        assert_eq!(history.pick((1, 1), 0), None);
+        assert_eq!(history.pick((1, 1), 1), Some(first));
+        assert_eq!(history.pick((13, 13), 0), None);
+        assert_eq!(history.bounds(eraser), None);
+        assert_eq!(history.bounds(second), Some([4, 4, 7, 7]));
+    }
+
+    #[test]
+    fn moving_a_shape_erases_its_old_cells(){
+        let mut history = History::new(20, 20, 10);
+        let id = history.push(&square((2, 2), 3, true));
+        let change = history.translate(id, (10, 1)).unwrap();
+        assert_eq!(change.get((2, 2)), Some(false));
+        assert_eq!(change.get((12, 3)), Some(true));
+        assert_eq!(history.bounds(id), Some([12, 3, 14, 5]));
+        assert_eq!(history.pick((13, 4), 0), Some(id));
+        assert_eq!(history.pick((3, 3), 0), None);
+        assert_eq!(solid(&history).len(), 9);
+
+        //Moving off the edge keeps what is left on the lattice
+        history.translate(id, (6, 0));
+        assert_eq!(history.bounds(id), Some([18, 3, 19, 5]));
+
+        history.undo();
+        history.undo();
+        assert_eq!(history.bounds(id), Some([2, 2, 4, 4]));
+        assert_eq!(solid(&history), history.cells(id).unwrap().get_mask().points().map(|p| (p.0, p.1)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn deleted_shapes_can_be_undone(){
+        let mut history = History::new(20, 20, 10);
+        let id = history.push(&square((2, 2), 3, true));
+        history.push(&square((3, 3), 3, true));
+        let change = history.delete(id).unwrap();
+        assert_eq!(change.len(), 9);
+        assert!(history.cells(id).is_none());
+        //Cells the later shape drew over the deleted one are erased with it
+        assert!(!history.is_solid(3 + 3 * 20));
+        assert!(history.delete(id).is_none());
+        history.undo();
+        assert_eq!(history.cells(id).unwrap().get_mask().len(), 9);
+        assert!(history.is_solid(3 + 3 * 20));
+    }
+}
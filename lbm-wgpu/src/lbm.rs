@@ -1,8 +1,8 @@
 use wgpu::{CommandEncoder, util::BufferInitDescriptor, BufferUsages, ShaderStages,BindGroupDescriptor};
-use std::{mem, borrow::Cow};
+use std::{mem, borrow::Cow, collections::BTreeMap};
 use wgpu::{Device, BindGroupEntry, util::DeviceExt, BindGroupLayout, ShaderModuleDescriptor, vertex_attr_array, VertexBufferLayout};
 
-use crate::{driver::Driver, barrier_shapes::{Shape, merge_shapes::get_points_vector, blob::Blob, line::Line}};
+use crate::{driver::Driver, camera::Camera, color_maps::{self, ColorStop}, barrier_shapes::{Shape, mask::Mask, primitive::Primitive}};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -21,6 +21,54 @@ pub enum ColorMap {
     Inferno,
     Viridis,
     Jet,
+    Plasma,
+    Magma,
+    Cividis,
+    Turbo,
+    Coolwarm,
+    Grayscale,
+    Sunset,
+    Custom,
+}
+
+//Which values the color map spans: the map's built in range, the values on screen (optionally symmetric about
+//zero) found on the GPU every frame, or a fixed range chosen by the user
+#[wasm_bindgen]
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ColorRange {
+    MapDefault,
+    Auto,
+    SymmetricAuto,
+    Fixed,
+}
+
+//How the field is shown: the summary statistic through the color map, or a line integral convolution of the
+//velocity tracing out streamlines, either on its own or shading the color map of speed or curl
+#[wasm_bindgen]
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Visualization {
+    Heatmap,
+    Lic,
+    LicSpeed,
+    LicCurl,
+}
+
+//Whether velocity arrows are drawn in one color or colored by speed through the color map
+#[wasm_bindgen]
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum GlyphColor {
+    Fixed,
+    ColorMap,
+}
+
+//Velocity arrows on a grid every spacing cells. An arrow reaches across most of the spacing, and the top of the
+//color map, at reference_speed in lattice units, faster flow is drawn the same
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct GlyphSettings{
+    pub spacing: u32,
+    pub reference_speed: f32,
+    pub coloring: GlyphColor,
+    pub color: [f32; 3],
 }
 
 #[wasm_bindgen]
@@ -51,6 +99,7 @@ pub struct LBM{
     barrier_buffer: wgpu::Buffer,
     omega_buffer: wgpu::Buffer,
     vertex_buffer: wgpu::Buffer,
+    color_buffer: wgpu::Buffer,
 
     //Compute Pipelines
     cardinal_pre_collision: wgpu::ComputePipeline,
@@ -68,28 +117,73 @@ pub struct LBM{
     uy: wgpu::ComputePipeline,
     rho: wgpu::ComputePipeline,
     speed: wgpu::ComputePipeline,
-    pub color_map: ColorMap,
-    viridis: wgpu::ComputePipeline,
-    jet: wgpu::ComputePipeline,
-    inferno: wgpu::ComputePipeline,
+    color_map: ColorMap,
+    color_lut: wgpu::ComputePipeline,
+    //Lookup table and value range of the current color map, bound with the size buffer
+    lut_buffer: wgpu::Buffer,
+    range_buffer: wgpu::Buffer,
+    lut_bg: wgpu::BindGroup,
+    color_range: ColorRange,
+    fixed_range: (f32, f32),
+    //Automatic ranges: extremes, then a histogram between them, then the range with the clipped tails removed
+    extrema: wgpu::ComputePipeline,
+    histogram: wgpu::ComputePipeline,
+    resolve_range: wgpu::ComputePipeline,
+    range_stats: wgpu::Buffer,
+    range_settings: wgpu::Buffer,
+    range_bg: wgpu::BindGroup,
     summary_stat: SummaryStat,
+    visualization: Visualization,
+    lic: wgpu::ComputePipeline,
+    lic_params: wgpu::Buffer,
+    lic_bg: wgpu::BindGroup,
 
     //Render Pipeline
     render: wgpu::RenderPipeline,
+    camera_buffer: wgpu::Buffer,
+    camera_bg: wgpu::BindGroup,
+
+    //Overlay drawn over the field, never written into the barrier buffer
+    overlay: wgpu::RenderPipeline,
+    overlay_bg: wgpu::BindGroup,
+    overlay_cells: wgpu::Buffer,
+    //(cell index, kind) pairs currently in overlay_cells
+    overlay_state: Vec<u32>,
+
+    //Colorbar and text drawn in window cells over everything else, see annotation.rs
+    annotation: wgpu::RenderPipeline,
+    annotation_bg: wgpu::BindGroup,
+    annotation_lut_bg: wgpu::BindGroup,
+    annotation_cells: wgpu::Buffer,
+    annotation_state: Vec<u32>,
+
+    //Velocity arrows between the overlay and the annotations, none are drawn while glyph_count is 0
+    glyphs: wgpu::RenderPipeline,
+    glyph_data_bg: wgpu::BindGroup,
+    glyph_params: wgpu::Buffer,
+    glyph_params_bg: wgpu::BindGroup,
+    glyph_count: u32,
 
     //Barrier Update Pipelines
     barrier_draw: wgpu::ComputePipeline,
+    barrier_primitives: wgpu::ComputePipeline,
+    primitive_bgl: wgpu::BindGroupLayout,
+    //Primitive and vertex buffers with their bind group, grown to fit the largest batch so far
+    primitive_buffers: Option<(wgpu::Buffer, wgpu::Buffer, wgpu::BindGroup)>,
 
     //Barrier BGS
     draw_bg: wgpu::BindGroup,
 
-    //Update Barrier Buffers
-    draw_num: wgpu::Buffer,
-    draw_points: wgpu::Buffer,
+    //Update Barrier Buffers, a rectangle and bitmasks of the cells inside it to set
+    draw_rect: wgpu::Buffer,
+    draw_covered: wgpu::Buffer,
+    draw_solid: wgpu::Buffer,
 
     //tracking variables
     pub compute_step: usize,
     frame_number: usize,
+    //Speed the fluid was last set moving at, in lattice units
+    flow_speed: f32,
     work_group_size: usize,
 
     x: u32,
@@ -190,7 +284,7 @@ impl LBM{
                         ty: wgpu::BindingType::Buffer { 
                             ty: wgpu::BufferBindingType::Storage { read_only: false }, 
                             has_dynamic_offset: false, 
-                            min_binding_size: wgpu::BufferSize::new((3 * x as usize * y as usize * mem::size_of::<f32>()) as _,) 
+                            min_binding_size: wgpu::BufferSize::new((4 * x as usize * y as usize * mem::size_of::<f32>()) as _,) 
                         },
                         count: None,
                     }
@@ -199,13 +293,17 @@ impl LBM{
         })
     }
 
-    fn create_color_bg(device : &Device, color_bgl: &wgpu::BindGroupLayout, x: u32, y:u32) -> wgpu::BindGroup{
-        let color_vec = vec![0.0; 3 * x as usize * y as usize];
-        let color_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
+    //array<vec3<f32>> has a 16 byte stride, so each cell takes 4 floats
+    fn create_color_buffer(device : &Device, x: u32, y:u32) -> wgpu::Buffer{
+        let color_vec = vec![0.0_f32; 4 * x as usize * y as usize];
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
             label: None,
             contents: bytemuck::cast_slice(&color_vec),
-            usage: wgpu::BufferUsages::STORAGE,
-        });
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        })
+    }
+
+    fn create_color_bg(device : &Device, color_bgl: &wgpu::BindGroupLayout, color_buffer: &wgpu::Buffer) -> wgpu::BindGroup{
         device.create_bind_group(&wgpu::BindGroupDescriptor{ 
             label: None, 
             layout: &color_bgl, 
@@ -336,6 +434,83 @@ impl LBM{
         })
     }
 
+    //Extremes and a 256 bin histogram of the summary output, the automatic range settings and the range itself
+    fn create_range_bgl(device : &Device) -> wgpu::BindGroupLayout{
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor{ 
+            label: None, 
+            entries: &[
+                wgpu::BindGroupLayoutEntry{
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer { 
+                        ty: wgpu::BufferBindingType::Storage { read_only: false }, 
+                        has_dynamic_offset: false, 
+                        min_binding_size: wgpu::BufferSize::new((258 * mem::size_of::<u32>()) as _,) 
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry{
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer { 
+                        ty: wgpu::BufferBindingType::Uniform, 
+                        has_dynamic_offset: false, 
+                        min_binding_size: wgpu::BufferSize::new((2 * mem::size_of::<u32>()) as _,) 
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry{
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer { 
+                        ty: wgpu::BufferBindingType::Storage { read_only: false }, 
+                        has_dynamic_offset: false, 
+                        min_binding_size: wgpu::BufferSize::new((2 * mem::size_of::<f32>()) as _,) 
+                    },
+                    count: None,
+                }
+            ] 
+        })
+    }
+
+    fn create_lut_bgl(device : &Device) -> wgpu::BindGroupLayout{
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor{ 
+            label: None, 
+            entries: &[
+                wgpu::BindGroupLayoutEntry{
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer { 
+                        ty: wgpu::BufferBindingType::Uniform, 
+                        has_dynamic_offset: false, 
+                        min_binding_size: wgpu::BufferSize::new((mem::size_of::<u32>()) as _,) 
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry{
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer { 
+                        ty: wgpu::BufferBindingType::Storage { read_only: true }, 
+                        has_dynamic_offset: false, 
+                        min_binding_size: wgpu::BufferSize::new((4 * color_maps::LUT_SIZE * mem::size_of::<f32>()) as _,) 
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry{
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer { 
+                        ty: wgpu::BufferBindingType::Uniform, 
+                        has_dynamic_offset: false, 
+                        min_binding_size: wgpu::BufferSize::new((2 * mem::size_of::<f32>()) as _,) 
+                    },
+                    count: None,
+                }
+            ] 
+        })
+    }
+
     fn create_size_bg(device : &Device, size_buffer: &wgpu::Buffer, size_bgl: &BindGroupLayout) -> wgpu::BindGroup{
         device.create_bind_group(&wgpu::BindGroupDescriptor{ 
             label: None, 
@@ -495,11 +670,11 @@ impl LBM{
         data_single: &wgpu::BindGroupLayout,
         color: &wgpu::BindGroupLayout,
         barrier: &wgpu::BindGroupLayout,
-        size: &wgpu::BindGroupLayout
+        lut: &wgpu::BindGroupLayout
     ) -> wgpu::PipelineLayout{
         device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor{ 
             label: None, 
-            bind_group_layouts: &[color, data_single, barrier, size], 
+            bind_group_layouts: &[color, data_single, barrier, lut], 
             push_constant_ranges: &[] 
         })
     }
@@ -573,7 +748,7 @@ impl LBM{
         device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
             label: None,
             contents: bytemuck::cast_slice(barrier),
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
         })
     }
 
@@ -642,9 +817,28 @@ impl LBM{
         
     }
 
+    fn create_camera_bgl(driver: &Driver) -> wgpu::BindGroupLayout{
+        driver.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor{ 
+            label: None, 
+            entries: &[
+                wgpu::BindGroupLayoutEntry{
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer { 
+                        ty: wgpu::BufferBindingType::Uniform, 
+                        has_dynamic_offset: false, 
+                        min_binding_size: wgpu::BufferSize::new((4 * mem::size_of::<f32>()) as _,) 
+                    },
+                    count: None,
+                }
+            ] 
+        })
+    }
+
     fn create_render_pipeline(driver: &Driver,
                               colors: &BindGroupLayout,
-                              dimension_params: &BindGroupLayout
+                              dimension_params: &BindGroupLayout,
+                              camera: &BindGroupLayout
                             ) -> wgpu::RenderPipeline{
 
         let render_shader = driver.device.create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -654,7 +848,7 @@ impl LBM{
 
         let render_pipeline_layout = driver.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
-            bind_group_layouts: &[&colors, &dimension_params],
+            bind_group_layouts: &[&colors, &dimension_params, camera],
             push_constant_ranges: &[],
         });
 
@@ -685,34 +879,150 @@ impl LBM{
         })
     }
 
-    fn create_barrier_update_bgl(driver: &Driver, x: u32, y: u32) -> wgpu::BindGroupLayout{
+    //Cell quad corners as laid out in the vertex buffer
+    fn cell_vertex_layout() -> VertexBufferLayout<'static>{
+        VertexBufferLayout{
+            array_stride: 4 * 2,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &vertex_attr_array![0 => Float32x2],
+        }
+    }
+
+    //Velocity, density and barriers read by the glyphs' vertex shader
+    fn create_glyph_data_bgl(driver: &Driver, x: u32, y: u32) -> wgpu::BindGroupLayout{
+        let entry = |binding: u32| wgpu::BindGroupLayoutEntry{
+            binding,
+            visibility: ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer{ 
+                ty: wgpu::BufferBindingType::Storage { read_only: true }, 
+                has_dynamic_offset: false, 
+                min_binding_size: wgpu::BufferSize::new((x as usize * y as usize * mem::size_of::<f32>()) as _,)
+            },
+            count: None,
+        };
+        driver.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor{ 
+            label: None, 
+            entries: &[entry(0), entry(1), entry(2), entry(3)]
+        })
+    }
+
+    fn create_overlay_bgl(driver: &Driver, x: u32, y: u32) -> wgpu::BindGroupLayout{
         driver.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor{ 
             label: None, 
             entries: &[
             wgpu::BindGroupLayoutEntry{
                 binding: 0,
-                visibility: ShaderStages::COMPUTE,
+                visibility: ShaderStages::VERTEX,
                 ty: wgpu::BindingType::Buffer{ 
-                    ty: wgpu::BufferBindingType::Uniform, 
+                    ty: wgpu::BufferBindingType::Storage { read_only: true }, 
                     has_dynamic_offset: false, 
-                    min_binding_size: wgpu::BufferSize::new((1 * mem::size_of::<u32>()) as _,)
+                    min_binding_size: wgpu::BufferSize::new((2 * x as usize * y as usize * mem::size_of::<u32>()) as _,)
                 },
                 count: None,
+            }
+            ]
+        })
+    }
+
+    //Alpha blended over the field. The overlay and the annotations draw the cell quad from the vertex buffer,
+    //instanced only over the listed cells, while the glyphs build their arrows from the vertex index alone
+    fn create_overlay_pipeline(driver: &Driver,
+                               source: &'static str,
+                               bind_group_layouts: &[&BindGroupLayout],
+                               vertex_buffers: &[VertexBufferLayout]
+                            ) -> wgpu::RenderPipeline{
+
+        let overlay_shader = driver.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(source)),
+        });
+
+        let overlay_pipeline_layout = driver.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+
+        let swapchain_capabilities = driver.surface.get_capabilities(&driver.adapter);
+        let swapchain_format = swapchain_capabilities.formats[0];
+
+        driver.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Overlay"),
+            layout: Some(&overlay_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &overlay_shader,
+                entry_point: "vs_main",
+                buffers: vertex_buffers,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &overlay_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: swapchain_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    //Bitmasks are one bit per cell, so a rectangle covering the whole lattice needs x*y/32 words
+    fn mask_words(x: u32, y: u32) -> usize{
+        (x as usize * y as usize).div_ceil(32)
+    }
+
+    fn create_barrier_update_bgl(driver: &Driver, x: u32, y: u32) -> wgpu::BindGroupLayout{
+        let mask_entry = |binding: u32| wgpu::BindGroupLayoutEntry{
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer{ 
+                ty: wgpu::BufferBindingType::Storage { read_only: true }, 
+                has_dynamic_offset: false, 
+                min_binding_size: wgpu::BufferSize::new((Self::mask_words(x, y) * mem::size_of::<u32>()) as _,)
             },
+            count: None,
+        };
+        driver.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor{ 
+            label: None, 
+            entries: &[
             wgpu::BindGroupLayoutEntry{
-                binding: 1,
+                binding: 0,
                 visibility: ShaderStages::COMPUTE,
                 ty: wgpu::BindingType::Buffer{ 
-                    ty: wgpu::BufferBindingType::Storage { read_only: true }, 
+                    ty: wgpu::BufferBindingType::Uniform, 
                     has_dynamic_offset: false, 
-                    min_binding_size: wgpu::BufferSize::new((x as usize * y as usize * mem::size_of::<u32>()) as _,)
+                    min_binding_size: wgpu::BufferSize::new((5 * mem::size_of::<u32>()) as _,)
                 },
                 count: None,
-            }
+            },
+            mask_entry(1),
+            mask_entry(2),
             ]
         })
     }
 
+    //Buffer sizes change as batches grow, so only the binding types are fixed here
+    fn create_primitive_bgl(driver: &Driver) -> wgpu::BindGroupLayout{
+        let entry = |binding: u32| wgpu::BindGroupLayoutEntry{
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer{ 
+                ty: wgpu::BufferBindingType::Storage { read_only: true }, 
+                has_dynamic_offset: false, 
+                min_binding_size: None
+            },
+            count: None,
+        };
+        driver.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor{ 
+            label: None, 
+            entries: &[entry(0), entry(1)]
+        })
+    }
+
     fn create_barrier_update_pl(driver: &Driver, 
         update_bgl: &wgpu::BindGroupLayout, 
         barrier_bgl: &wgpu::BindGroupLayout) -> wgpu::PipelineLayout{
@@ -776,13 +1086,16 @@ impl LBM{
             &omega_buffer, 
             &size_buffer,
             &collide_bgl);
-        let density_bg = Self::create_data_bg(&driver.device, 
-            &[&zero_vec, &zero_vec, &zero_vec], 
+        //Kept so the glyphs can read them too
+        let density_buffers = Self::create_data_buffers(&driver.device, &vec![zero_vec.clone(); 3]);
+        let density_bg = Self::create_data_bg_from_buffers(&driver.device, 
+            &density_buffers.iter().collect(), 
             &data_triple_bgl);
         let output_bg = Self::create_data_bg(&driver.device, 
             &[&zero_vec], 
             &data_single_bgl);
-        let color_bg = Self::create_color_bg(&driver.device, &color_bgl, x, y);
+        let color_buffer = Self::create_color_buffer(&driver.device, x, y);
+        let color_bg = Self::create_color_bg(&driver.device, &color_bgl, &color_buffer);
         let size_bg = Self::create_size_bg(&driver.device, &size_buffer, &size_bgl);
         let dimension_bg = Self::create_dimension_bg(&driver.device, &dimension_bgl, x, y);
         let vertex_dimension_bg = Self::create_vertex_dimension_bg(&driver.device, &dimension_vertex_bgl, x, y);
@@ -811,11 +1124,13 @@ impl LBM{
             &data_triple_bgl, 
             &data_single_bgl);
 
+        let lut_bgl = Self::create_lut_bgl(&driver.device);
+
         let color_map_pl = Self::create_color_map_pl(&driver.device,
             &data_single_bgl,
             &color_bgl,
             &barrier_bgl,
-            &size_bgl
+            &lut_bgl
         );
 
         //Create all shader modules
@@ -884,19 +1199,9 @@ impl LBM{
             source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("rewritten_shaders/summary_stats/curl.wgsl")))
         });
 
-        let inferno_s = driver.device.create_shader_module(ShaderModuleDescriptor{ 
-            label: None, 
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("rewritten_shaders/color_map/inferno.wgsl")))
-        });
-
-        let viridis_s = driver.device.create_shader_module(ShaderModuleDescriptor{ 
+        let lut_s = driver.device.create_shader_module(ShaderModuleDescriptor{ 
             label: None, 
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("rewritten_shaders/color_map/viridis.wgsl")))
-        });
-
-        let jet_s = driver.device.create_shader_module(ShaderModuleDescriptor{ 
-            label: None, 
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("rewritten_shaders/color_map/jet.wgsl")))
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("rewritten_shaders/color_map/lut.wgsl")))
         });
 
         let corner_pre_collision = Self::create_compute_pipeline(&driver.device, 
@@ -946,27 +1251,299 @@ impl LBM{
             &speed_s, 
             &summary_pl);
 
-        let viridis = Self::create_compute_pipeline(&driver.device, 
-            &viridis_s, 
+        let color_lut = Self::create_compute_pipeline(&driver.device, 
+            &lut_s, 
             &color_map_pl);
 
-        let inferno = Self::create_compute_pipeline(&driver.device, 
-            &inferno_s, 
-            &color_map_pl);
+        let lut_buffer = driver.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
+            label: None,
+            contents: bytemuck::cast_slice(&color_maps::lut(&color_maps::stops(ColorMap::Jet).unwrap())),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        });
 
-        let jet = Self::create_compute_pipeline(&driver.device, 
-            &jet_s, 
-            &color_map_pl);
+        let (low, high) = color_maps::default_range(ColorMap::Jet);
+        let range_buffer = driver.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
+            label: None,
+            contents: bytemuck::cast_slice(&[low, high]),
+            usage: BufferUsages::UNIFORM | BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+        });
+
+        let lut_bg = driver.device.create_bind_group(&BindGroupDescriptor{
+            label: None,
+            layout: &lut_bgl,
+            entries: &[BindGroupEntry{
+                binding: 0,
+                resource: size_buffer.as_entire_binding(),
+            },
+            BindGroupEntry{
+                binding: 1,
+                resource: lut_buffer.as_entire_binding(),
+            },
+            BindGroupEntry{
+                binding: 2,
+                resource: range_buffer.as_entire_binding(),
+            }]
+        });
+
+        let lic_bgl = driver.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor{ 
+            label: None, 
+            entries: &[
+                wgpu::BindGroupLayoutEntry{
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer { 
+                        ty: wgpu::BufferBindingType::Uniform, 
+                        has_dynamic_offset: false, 
+                        min_binding_size: wgpu::BufferSize::new((3 * mem::size_of::<u32>()) as _,) 
+                    },
+                    count: None,
+                }
+            ] 
+        });
+
+        let lic_pl = driver.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor{ 
+            label: None, 
+            bind_group_layouts: &[&color_bgl, &data_triple_bgl, &barrier_bgl, &lic_bgl], 
+            push_constant_ranges: &[] 
+        });
+
+        let lic_s = driver.device.create_shader_module(ShaderModuleDescriptor{ 
+            label: None, 
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("rewritten_shaders/visualization/lic.wgsl")))
+        });
+
+        let lic = Self::create_compute_pipeline(&driver.device, &lic_s, &lic_pl);
+
+        //Lattice width and height, then whether to shade the color map (see lic.wgsl)
+        let lic_params = driver.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
+            label: None,
+            contents: bytemuck::cast_slice(&[x, y, 0, 0]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let lic_bg = driver.device.create_bind_group(&BindGroupDescriptor{
+            label: None,
+            layout: &lic_bgl,
+            entries: &[BindGroupEntry{
+                binding: 0,
+                resource: lic_params.as_entire_binding(),
+            }]
+        });
+
+        let range_bgl = Self::create_range_bgl(&driver.device);
+
+        let range_pl = driver.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor{ 
+            label: None, 
+            bind_group_layouts: &[&data_single_bgl, &barrier_bgl, &size_bgl, &range_bgl], 
+            push_constant_ranges: &[] 
+        });
+
+        let extrema_s = driver.device.create_shader_module(ShaderModuleDescriptor{ 
+            label: None, 
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("rewritten_shaders/color_range/extrema.wgsl")))
+        });
+
+        let histogram_s = driver.device.create_shader_module(ShaderModuleDescriptor{ 
+            label: None, 
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("rewritten_shaders/color_range/histogram.wgsl")))
+        });
+
+        let resolve_s = driver.device.create_shader_module(ShaderModuleDescriptor{ 
+            label: None, 
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("rewritten_shaders/color_range/resolve.wgsl")))
+        });
+
+        let extrema = Self::create_compute_pipeline(&driver.device, &extrema_s, &range_pl);
+
+        let histogram = Self::create_compute_pipeline(&driver.device, &histogram_s, &range_pl);
+
+        let resolve_range = Self::create_compute_pipeline(&driver.device, &resolve_s, &range_pl);
+
+        let range_stats = driver.device.create_buffer(&wgpu::BufferDescriptor{
+            label: None,
+            size: (258 * mem::size_of::<u32>()) as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let range_settings = driver.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
+            label: None,
+            contents: bytemuck::cast_slice(&[0_u32, 0_u32]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let range_bg = driver.device.create_bind_group(&BindGroupDescriptor{
+            label: None,
+            layout: &range_bgl,
+            entries: &[BindGroupEntry{
+                binding: 0,
+                resource: range_stats.as_entire_binding(),
+            },
+            BindGroupEntry{
+                binding: 1,
+                resource: range_settings.as_entire_binding(),
+            },
+            BindGroupEntry{
+                binding: 2,
+                resource: range_buffer.as_entire_binding(),
+            }]
+        });
+
+        let camera_bgl = Self::create_camera_bgl(driver);
+
+        let camera_buffer = driver.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
+            label: None,
+            contents: bytemuck::cast_slice(&Camera::new(x, y).uniform()),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let camera_bg = driver.device.create_bind_group(&BindGroupDescriptor{
+            label: None,
+            layout: &camera_bgl,
+            entries: &[BindGroupEntry{
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }]
+        });
 
         let render = Self::create_render_pipeline(&driver, 
             &color_bgl, 
-            &dimension_vertex_bgl);
+            &dimension_vertex_bgl,
+            &camera_bgl);
 
         let vertex_buffer = Self::create_vertex_buffer(driver, x, y);
 
+        let overlay_bgl = Self::create_overlay_bgl(driver, x, y);
+
+        let overlay = Self::create_overlay_pipeline(driver, 
+            include_str!("rewritten_shaders/overlay.wgsl"), 
+            &[&overlay_bgl, &dimension_vertex_bgl, &camera_bgl],
+            &[Self::cell_vertex_layout()]);
+
+        let overlay_cells = driver.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
+            label: None,
+            contents: bytemuck::cast_slice(&vec![0 as u32;2 * x as usize * y as usize]),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        });
+
+        let overlay_bg = driver.device.create_bind_group(&BindGroupDescriptor{
+            label: None,
+            layout: &overlay_bgl,
+            entries: &[BindGroupEntry{
+                binding: 0,
+                resource: overlay_cells.as_entire_binding(),
+            }]
+        });
+
+        let annotation_lut_bgl = driver.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor{ 
+            label: None, 
+            entries: &[
+            wgpu::BindGroupLayoutEntry{
+                binding: 0,
+                visibility: ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer{ 
+                    ty: wgpu::BufferBindingType::Storage { read_only: true }, 
+                    has_dynamic_offset: false, 
+                    min_binding_size: wgpu::BufferSize::new((4 * color_maps::LUT_SIZE * mem::size_of::<f32>()) as _,)
+                },
+                count: None,
+            }
+            ]
+        });
+
+        let annotation = Self::create_overlay_pipeline(driver, 
+            include_str!("rewritten_shaders/annotation.wgsl"), 
+            &[&overlay_bgl, &dimension_vertex_bgl, &annotation_lut_bgl],
+            &[Self::cell_vertex_layout()]);
+
+        let annotation_cells = driver.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
+            label: None,
+            contents: bytemuck::cast_slice(&vec![0_u32; 2 * x as usize * y as usize]),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        });
+
+        let annotation_bg = driver.device.create_bind_group(&BindGroupDescriptor{
+            label: None,
+            layout: &overlay_bgl,
+            entries: &[BindGroupEntry{
+                binding: 0,
+                resource: annotation_cells.as_entire_binding(),
+            }]
+        });
+
+        let annotation_lut_bg = driver.device.create_bind_group(&BindGroupDescriptor{
+            label: None,
+            layout: &annotation_lut_bgl,
+            entries: &[BindGroupEntry{
+                binding: 0,
+                resource: lut_buffer.as_entire_binding(),
+            }]
+        });
+
+        let glyph_data_bgl = Self::create_glyph_data_bgl(driver, x, y);
+
+        let glyph_params_bgl = driver.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor{ 
+            label: None, 
+            entries: &[
+            wgpu::BindGroupLayoutEntry{
+                binding: 0,
+                visibility: ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer{ 
+                    ty: wgpu::BufferBindingType::Uniform, 
+                    has_dynamic_offset: false, 
+                    min_binding_size: wgpu::BufferSize::new((12 * mem::size_of::<u32>()) as _,)
+                },
+                count: None,
+            }
+            ]
+        });
+
+        let glyphs = Self::create_overlay_pipeline(driver, 
+            include_str!("rewritten_shaders/glyphs.wgsl"), 
+            &[&glyph_data_bgl, &glyph_params_bgl, &camera_bgl, &annotation_lut_bgl],
+            &[]);
+
+        let glyph_data_bg = driver.device.create_bind_group(&BindGroupDescriptor{
+            label: None,
+            layout: &glyph_data_bgl,
+            entries: &[
+            BindGroupEntry{
+                binding: 0,
+                resource: density_buffers[0].as_entire_binding(),
+            },
+            BindGroupEntry{
+                binding: 1,
+                resource: density_buffers[1].as_entire_binding(),
+            },
+            BindGroupEntry{
+                binding: 2,
+                resource: density_buffers[2].as_entire_binding(),
+            },
+            BindGroupEntry{
+                binding: 3,
+                resource: barrier_buffer.as_entire_binding(),
+            }]
+        });
+
+        //Laid out as Params in glyphs.wgsl, filled in by set_glyphs
+        let glyph_params = driver.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
+            label: None,
+            contents: bytemuck::cast_slice(&[0_u32; 12]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let glyph_params_bg = driver.device.create_bind_group(&BindGroupDescriptor{
+            label: None,
+            layout: &glyph_params_bgl,
+            entries: &[BindGroupEntry{
+                binding: 0,
+                resource: glyph_params.as_entire_binding(),
+            }]
+        });
+
         let draw_s = driver.device.create_shader_module(ShaderModuleDescriptor{ 
             label: None, 
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("rewritten_shaders/update_barrier/barrier_draw.wgsl")))
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("rewritten_shaders/update_barrier/barrier_rect.wgsl")))
         });
 
         let barrier_update_bgl = Self::create_barrier_update_bgl(driver, x, y);
@@ -975,15 +1552,38 @@ impl LBM{
 
         let barrier_draw = Self::create_compute_pipeline(&driver.device, &draw_s, &barrier_update_pl);
 
-        let draw_points = driver.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
+        let primitives_s = driver.device.create_shader_module(ShaderModuleDescriptor{ 
+            label: None, 
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("rewritten_shaders/update_barrier/barrier_primitives.wgsl")))
+        });
+
+        let primitive_bgl = Self::create_primitive_bgl(driver);
+
+        let primitive_pl = driver.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor{ 
+            label: None, 
+            bind_group_layouts: &[&primitive_bgl, &barrier_bgl, &dimension_bgl], 
+            push_constant_ranges: &[]
+        });
+
+        let barrier_primitives = Self::create_compute_pipeline(&driver.device, &primitives_s, &primitive_pl);
+
+        let mask_words = Self::mask_words(x, y);
+
+        let draw_covered = driver.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
             label: None,
-            contents: bytemuck::cast_slice(&vec![0 as u32;2 * x as usize * y as usize]),
+            contents: bytemuck::cast_slice(&vec![0_u32; mask_words]),
             usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
         });
 
-        let draw_num = driver.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
+        let draw_solid = driver.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
             label: None,
-            contents: bytemuck::bytes_of(&0),
+            contents: bytemuck::cast_slice(&vec![0_u32; mask_words]),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        });
+
+        let draw_rect = driver.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
+            label: None,
+            contents: bytemuck::cast_slice(&[0_u32; 5]),
             usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
         });
 
@@ -992,11 +1592,15 @@ impl LBM{
             layout: &barrier_update_bgl,
             entries: &[BindGroupEntry{
                 binding: 0,
-                resource: draw_num.as_entire_binding(),
+                resource: draw_rect.as_entire_binding(),
             },
             BindGroupEntry{
                 binding: 1,
-                resource: draw_points.as_entire_binding(),
+                resource: draw_covered.as_entire_binding(),
+            },
+            BindGroupEntry{
+                binding: 2,
+                resource: draw_solid.as_entire_binding(),
             }]
         });
 
@@ -1023,24 +1627,59 @@ impl LBM{
             speed, 
             color_map: ColorMap::Jet, 
             render,
+            camera_buffer,
+            camera_bg,
+            overlay,
+            overlay_bg,
+            overlay_cells,
+            overlay_state: Vec::<u32>::new(),
+            annotation,
+            annotation_bg,
+            annotation_lut_bg,
+            annotation_cells,
+            annotation_state: Vec::<u32>::new(),
+            glyphs,
+            glyph_data_bg,
+            glyph_params,
+            glyph_params_bg,
+            glyph_count: 0,
             cardinal_pre_collision,
             corner_pre_collision,
             corner_collide: corner_collision,
             cardinal_collide: cardinal_collision,
             compute_step: 0,
             frame_number: 0,
+            flow_speed: 0.1,
             work_group_size: Self::calculate_work_group_size(x, y),
             size_bg,
             color_bg,
             vertex_buffer,
+            color_buffer,
             summary_stat: SummaryStat::Curl,
+            visualization: Visualization::Heatmap,
+            lic,
+            lic_params,
+            lic_bg,
             barrier_draw,
+            barrier_primitives,
+            primitive_bgl,
+            primitive_buffers: None,
             draw_bg,
-            draw_num,
-            draw_points,
-            viridis,
-            jet,
-            inferno,
+            draw_rect,
+            draw_covered,
+            draw_solid,
+            color_lut,
+            lut_buffer,
+            range_buffer,
+            lut_bg,
+            color_range: ColorRange::MapDefault,
+            fixed_range: (0.0, 0.0),
+            extrema,
+            histogram,
+            resolve_range,
+            range_stats,
+            range_settings,
+            range_bg,
             rho,
             data_buffers,
             x,
@@ -1049,7 +1688,10 @@ impl LBM{
     }
 
     fn calculate_summary(&mut self, encoder: &mut CommandEncoder){
-        match self.summary_stat {
+        let Some(summary_stat) = self.shown_summary() else {
+            return;
+        };
+        match summary_stat {
             SummaryStat::Curl => self.curl(encoder),
             SummaryStat::Rho => self.rho(encoder),
             SummaryStat::Ux => self.ux( encoder),
@@ -1062,19 +1704,45 @@ impl LBM{
         self.summary_stat = stat
     }
 
+    pub fn set_visualization(&mut self, driver: &Driver, visualization: Visualization){
+        self.visualization = visualization;
+        let modulate = u32::from(visualization == Visualization::LicSpeed || visualization == Visualization::LicCurl);
+        driver.queue.write_buffer(&self.lic_params, 2 * mem::size_of::<u32>() as u64, bytemuck::bytes_of(&modulate));
+    }
+
+    //Statistic the color map is showing, the shaded convolutions pick their own and the plain one shows none
+    pub fn shown_summary(&self) -> Option<SummaryStat>{
+        match self.visualization {
+            Visualization::Heatmap => Some(self.summary_stat),
+            Visualization::Lic => None,
+            Visualization::LicSpeed => Some(SummaryStat::Speed),
+            Visualization::LicCurl => Some(SummaryStat::Curl),
+        }
+    }
+
     pub fn iterate(&mut self, driver: &Driver, compute_steps: usize){
+        self.advance(driver, compute_steps);
+        self.rerender(driver);
+    }
+
+    //Compute steps without coloring or rendering the result
+    pub fn advance(&mut self, driver: &Driver, compute_steps: usize){
         for _ in 0..compute_steps{
             self.compute_step(driver);
         }
+    }
+
+    //Brings the color buffer up to date with the current step without rendering it
+    pub fn update_colors(&mut self, driver: &Driver){
         let mut encoder = driver.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
         self.calculate_summary(&mut encoder);
         self.color_map(&mut encoder);
         driver.queue.submit(Some(encoder.finish()));
-        self.render(driver);
     }
 
     pub fn reset_to_equilibrium(&mut self, driver : &Driver){
         let equilibrium_state = Self::set_equil(0.1, 0.0, 1.0, self.x, self.y);
+        self.flow_speed = 0.1;
         for i in 0..9{
             driver.queue.write_buffer(&self.data_buffers[0][i], 0, bytemuck::cast_slice(&equilibrium_state[i]));
             driver.queue.write_buffer(&self.data_buffers[1][i], 0, bytemuck::cast_slice(&equilibrium_state[i]));
@@ -1089,6 +1757,7 @@ impl LBM{
 
     pub fn custom_speed(&mut self, driver : &Driver, ux: f32){
         let equilibrium_state = Self::set_equil(ux, 0.0, 1.0, self.x, self.y);
+        self.flow_speed = ux;
         for i in 0..9{
             driver.queue.write_buffer(&self.data_buffers[0][i], 0, bytemuck::cast_slice(&equilibrium_state[i]));
             driver.queue.write_buffer(&self.data_buffers[1][i], 0, bytemuck::cast_slice(&equilibrium_state[i]));
@@ -1102,10 +1771,7 @@ impl LBM{
     }
 
     pub fn rerender(&mut self, driver: &Driver){
-        let mut encoder = driver.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-        self.calculate_summary(&mut encoder);
-        self.color_map(&mut encoder);
-        driver.queue.submit(Some(encoder.finish()));
+        self.update_colors(driver);
         self.render(driver);
     }
 
@@ -1156,13 +1822,41 @@ impl LBM{
             rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
             rpass.set_bind_group(0, &self.color_bg, &[]);
             rpass.set_bind_group(1, &self.dimension_bg_vertex, &[]);
+            rpass.set_bind_group(2, &self.camera_bg, &[]);
             rpass.draw(0..6, 0..self.x*self.y);
+            //Each layer sets every group its pipeline declares, the passes before it leave different ones bound
+            if !self.overlay_state.is_empty(){
+                rpass.set_pipeline(&self.overlay);
+                rpass.set_bind_group(0, &self.overlay_bg, &[]);
+                rpass.set_bind_group(1, &self.dimension_bg_vertex, &[]);
+                rpass.set_bind_group(2, &self.camera_bg, &[]);
+                rpass.draw(0..6, 0..self.overlay_state.len() as u32/2);
+            }
+            if self.glyph_count > 0{
+                rpass.set_pipeline(&self.glyphs);
+                rpass.set_bind_group(0, &self.glyph_data_bg, &[]);
+                rpass.set_bind_group(1, &self.glyph_params_bg, &[]);
+                rpass.set_bind_group(2, &self.camera_bg, &[]);
+                rpass.set_bind_group(3, &self.annotation_lut_bg, &[]);
+                rpass.draw(0..9, 0..self.glyph_count);
+            }
+            if !self.annotation_state.is_empty(){
+                rpass.set_pipeline(&self.annotation);
+                rpass.set_bind_group(0, &self.annotation_bg, &[]);
+                rpass.set_bind_group(1, &self.dimension_bg_vertex, &[]);
+                rpass.set_bind_group(2, &self.annotation_lut_bg, &[]);
+                rpass.draw(0..6, 0..self.annotation_state.len() as u32/2);
+            }
         }
         driver.queue.submit(Some(encoder.finish()));
         frame.present();
         self.frame_number += 1;
     }
 
+    pub fn set_camera(&self, driver: &Driver, camera: &Camera){
+        driver.queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&camera.uniform()));
+    }
+
     pub fn get_frame_num(&self) -> usize{
         self.frame_number
     }
@@ -1171,6 +1865,18 @@ impl LBM{
         self.compute_step
     }
 
+    pub fn get_flow_speed(&self) -> f32{
+        self.flow_speed
+    }
+
+    pub fn get_dimensions(&self) -> (u32, u32){
+        (self.x, self.y)
+    }
+
+    pub fn copy_colors(&self, encoder: &mut CommandEncoder, destination: &wgpu::Buffer){
+        encoder.copy_buffer_to_buffer(&self.color_buffer, 0, destination, 0, self.color_buffer.size());
+    }
+
     fn pre_collide_corner(&mut self, encoder: &mut CommandEncoder){
         let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("Precollision-corner") });
         cpass.set_pipeline(&self.corner_pre_collision);
@@ -1297,50 +2003,154 @@ impl LBM{
     }
 
     pub fn color_map(&mut self,  encoder: &mut CommandEncoder){
-        match self.color_map {
-            ColorMap::Inferno => self.inferno_map(encoder),
-            ColorMap::Viridis => self.viridis_map(encoder),
-            ColorMap::Jet => self.jet_map(encoder),
+        if self.shown_summary().is_some(){
+            if self.color_range == ColorRange::Auto || self.color_range == ColorRange::SymmetricAuto{
+                self.find_range(encoder);
+            }
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+            cpass.set_pipeline(&self.color_lut);
+            cpass.set_bind_group(0, &self.color_bg, &[]);
+            cpass.set_bind_group(1, &self.output_bg, &[]);
+            cpass.set_bind_group(2, &self.barrier_bg, &[]);
+            cpass.set_bind_group(3, &self.lut_bg, &[]);
+            cpass.dispatch_workgroups(self.work_group_size as u32, 1, 1);
+        }
+        if self.visualization != Visualization::Heatmap{
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("LIC") });
+            cpass.set_pipeline(&self.lic);
+            cpass.set_bind_group(0, &self.color_bg, &[]);
+            cpass.set_bind_group(1, &self.density_bg, &[]);
+            cpass.set_bind_group(2, &self.barrier_bg, &[]);
+            cpass.set_bind_group(3, &self.lic_bg, &[]);
+            cpass.dispatch_workgroups(self.work_group_size as u32, 1, 1);
         }
     }
 
-    fn viridis_map(&mut self,  encoder: &mut CommandEncoder){
+    //Writes the range of the summary output into the range buffer the color map kernel reads
+    fn find_range(&mut self, encoder: &mut CommandEncoder){
+        encoder.clear_buffer(&self.range_stats, 0, None);
         let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
-        cpass.set_pipeline(&self.viridis);
-        cpass.set_bind_group(0, &self.color_bg, &[]);
-        cpass.set_bind_group(1, &self.output_bg, &[]);
-        cpass.set_bind_group(2, &self.barrier_bg, &[]);
-        cpass.set_bind_group(3, &self.size_bg, &[]);
+        cpass.set_bind_group(0, &self.output_bg, &[]);
+        cpass.set_bind_group(1, &self.barrier_bg, &[]);
+        cpass.set_bind_group(2, &self.size_bg, &[]);
+        cpass.set_bind_group(3, &self.range_bg, &[]);
+        cpass.set_pipeline(&self.extrema);
         cpass.dispatch_workgroups(self.work_group_size as u32, 1, 1);
+        cpass.set_pipeline(&self.histogram);
+        cpass.dispatch_workgroups(self.work_group_size as u32, 1, 1);
+        cpass.set_pipeline(&self.resolve_range);
+        cpass.dispatch_workgroups(1, 1, 1);
     }
 
-    fn jet_map(&mut self,  encoder: &mut CommandEncoder){
-        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
-        cpass.set_pipeline(&self.jet);
-        cpass.set_bind_group(0, &self.color_bg, &[]);
-        cpass.set_bind_group(1, &self.output_bg, &[]);
-        cpass.set_bind_group(2, &self.barrier_bg, &[]);
-        cpass.set_bind_group(3, &self.size_bg, &[]);
-        cpass.dispatch_workgroups(self.work_group_size as u32, 1, 1);
+    //Uploads the lookup table built from the stops, along with the map's value range if that is in use
+    pub fn set_color_map(&mut self, driver: &Driver, color_map: ColorMap, stops: &[ColorStop]){
+        self.color_map = color_map;
+        driver.queue.write_buffer(&self.lut_buffer, 0, bytemuck::cast_slice(&color_maps::lut(stops)));
+        if self.color_range == ColorRange::MapDefault{
+            let (low, high) = color_maps::default_range(color_map);
+            driver.queue.write_buffer(&self.range_buffer, 0, bytemuck::cast_slice(&[low, high]));
+        }
     }
 
-    fn inferno_map(&mut self,  encoder: &mut CommandEncoder){
-        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
-        cpass.set_pipeline(&self.inferno);
-        cpass.set_bind_group(0, &self.color_bg, &[]);
-        cpass.set_bind_group(1, &self.output_bg, &[]);
-        cpass.set_bind_group(2, &self.barrier_bg, &[]);
-        cpass.set_bind_group(3, &self.size_bg, &[]);
-        cpass.dispatch_workgroups(self.work_group_size as u32, 1, 1);
+    //Fixed uses low and high, the automatic ranges leave out clip of the cells at each end
+    pub fn set_color_range(&mut self, driver: &Driver, color_range: ColorRange, low: f32, high: f32, clip: f32){
+        self.color_range = color_range;
+        match color_range {
+            ColorRange::MapDefault => {
+                let (low, high) = color_maps::default_range(self.color_map);
+                driver.queue.write_buffer(&self.range_buffer, 0, bytemuck::cast_slice(&[low, high]));
+            },
+            ColorRange::Fixed => {
+                self.fixed_range = (low, high);
+                driver.queue.write_buffer(&self.range_buffer, 0, bytemuck::cast_slice(&[low, high]));
+            },
+            ColorRange::Auto | ColorRange::SymmetricAuto => {
+                let mode = u32::from(color_range == ColorRange::SymmetricAuto);
+                driver.queue.write_buffer(&self.range_settings, 0, bytemuck::cast_slice(&[mode, clip.to_bits()]));
+            },
+        }
+    }
+
+    //The range the color map spans, None when the GPU finds it and it has to be read back or nothing is mapped
+    pub fn get_color_range(&self) -> Option<(f32, f32)>{
+        self.shown_summary()?;
+        match self.color_range {
+            ColorRange::MapDefault => Some(color_maps::default_range(self.color_map)),
+            ColorRange::Fixed => Some(self.fixed_range),
+            ColorRange::Auto | ColorRange::SymmetricAuto => None,
+        }
+    }
+
+    pub fn copy_color_range(&self, encoder: &mut CommandEncoder, destination: &wgpu::Buffer){
+        encoder.copy_buffer_to_buffer(&self.range_buffer, 0, destination, 0, self.range_buffer.size());
+    }
+
+    //Shows velocity arrows, or hides them for None
+    pub fn set_glyphs(&mut self, driver: &Driver, glyphs: Option<GlyphSettings>){
+        let Some(glyphs) = glyphs else {
+            self.glyph_count = 0;
+            return;
+        };
+        let spacing = glyphs.spacing.max(1);
+        let [r, g, b] = glyphs.color.map(f32::to_bits);
+        let params = [self.x, self.y, spacing, u32::from(glyphs.coloring == GlyphColor::ColorMap),
+            glyphs.reference_speed.to_bits(), 0, 0, 0,
+            r, g, b, 1.0_f32.to_bits()];
+        driver.queue.write_buffer(&self.glyph_params, 0, bytemuck::cast_slice(&params));
+        //Matches the grid in glyphs.wgsl
+        self.glyph_count = (self.x/spacing).max(1) * (self.y/spacing).max(1);
+    }
+
+    //Replaces the annotation layer, returns true if it changed. See set_overlay
+    pub fn set_annotation(&mut self, driver: &Driver, cells: Vec<u32>) -> bool{
+        if cells == self.annotation_state{
+            return false;
+        }
+        if !cells.is_empty(){
+            driver.queue.write_buffer(&self.annotation_cells, 0, bytemuck::cast_slice(&cells));
+        }
+        self.annotation_state = cells;
+        true
+    }
+
+    //Shows the pending shape's cells and an outline (the brush cursor) until the next call, returns true if
+    //the overlay changed so a paused field knows to render again
+    pub fn set_overlay(&mut self, driver: &Driver, pending: &dyn Shape, outline: &[(isize, isize)]) -> bool{
+        let (xdim, ydim) = (self.x as isize, self.y as isize);
+        let mut kinds = BTreeMap::<u32, u32>::new();
+        for p in pending.get_mask().points().filter(|p| p.0 >= 0 && p.1 >= 0 && p.0 < xdim && p.1 < ydim){
+            kinds.insert(self.index(p.0 as usize, p.1 as usize) as u32, if p.2 { 1 } else { 2 });
+        }
+        for p in outline.iter().filter(|p| p.0 >= 0 && p.1 >= 0 && p.0 < xdim && p.1 < ydim){
+            kinds.insert(self.index(p.0 as usize, p.1 as usize) as u32, 3);
+        }
+        let cells: Vec<u32> = kinds.iter().flat_map(|(index, kind)| [*index, *kind]).collect();
+        if cells == self.overlay_state{
+            return false;
+        }
+        if !cells.is_empty(){
+            driver.queue.write_buffer(&self.overlay_cells, 0, bytemuck::cast_slice(&cells));
+        }
+        self.overlay_state = cells;
+        true
     }
 
     pub fn draw_shape(&mut self, driver : &Driver, shape: &dyn Shape){
-        self.draw_barrier_updates(driver, get_points_vector(shape, self.x as usize));
+        self.draw_mask(driver, shape.get_mask());
     }
 
-    fn draw_barrier_updates(&mut self,  driver : &Driver, points : Vec<u32>){
-        driver.queue.write_buffer(&self.draw_points, 0, bytemuck::cast_slice(&points));
-        driver.queue.write_buffer(&self.draw_num, 0, bytemuck::bytes_of(&(points.len() as u32 - 1)));
+    //Uploads only the mask's rectangle, two bits per cell, rather than every point
+    pub fn draw_mask(&mut self, driver : &Driver, mask: &Mask){
+        let mask = mask.clip(self.x as isize, self.y as isize);
+        if mask.is_empty(){
+            return;
+        }
+        let (origin, width, height) = mask.rectangle();
+        let (covered, solid) = mask.words();
+        let rect = [origin.0 as u32, origin.1 as u32, width as u32, height as u32, self.x];
+        driver.queue.write_buffer(&self.draw_rect, 0, bytemuck::cast_slice(&rect));
+        driver.queue.write_buffer(&self.draw_covered, 0, bytemuck::cast_slice(covered));
+        driver.queue.write_buffer(&self.draw_solid, 0, bytemuck::cast_slice(solid));
         driver.queue.submit(None);
 
         let mut encoder = driver.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
@@ -1349,134 +2159,85 @@ impl LBM{
         cpass.set_pipeline(&self.barrier_draw);
         cpass.set_bind_group(0, &self.draw_bg, &[]);
         cpass.set_bind_group(1, &self.barrier_bg, &[]);
-        let work_groups = (points.len()/2) as u32;
+        let work_groups = (width * height).div_ceil(256) as u32;
         cpass.dispatch_workgroups(work_groups, 1, 1);
         }
         driver.queue.submit(Some(encoder.finish()));
     }
 
-    pub fn update_omega_buffer(&mut self,  driver : &Driver, omega: f32){
-        driver.queue.write_buffer(&self.omega_buffer, 0, bytemuck::bytes_of(&omega));
-    }
+    //Rasterizes the primitives on the GPU over their bounding boxes, so only their parameters are uploaded.
+    //Every primitive in a call gets the same value since they are drawn in no particular order
+    pub fn draw_primitives(&mut self, driver : &Driver, primitives: &[Primitive], value: bool){
+        //Workgroup counts are limited to 65535 per dimension and each primitive takes one along z
+        for batch in primitives.chunks(65535){
+            let mut vertices = Vec::<i32>::new();
+            let encoded: Vec<[u32; 16]> = batch.iter().filter_map(|p| p.encode(value, self.x, self.y, &mut vertices)).collect();
+            if encoded.is_empty(){
+                continue;
+            }
+            let max_width = encoded.iter().map(|p| p[6]).max().unwrap_or(0);
+            let max_height = encoded.iter().map(|p| p[7]).max().unwrap_or(0);
+            if vertices.is_empty(){
+                vertices.extend([0, 0]);
+            }
 
-    pub fn reset_barrier(&mut self, driver : &Driver){
-        let barrier_reset = Self::init_barrier(self.x, self.y);
-        driver.queue.write_buffer(&self.barrier_buffer, 0, bytemuck::cast_slice(&barrier_reset));
+            let words: Vec<u32> = encoded.concat();
+            self.reserve_primitive_buffers(driver, words.len() * 4, vertices.len() * 4);
+            let (primitive_buffer, vertex_buffer, bind_group) = self.primitive_buffers.as_ref().unwrap();
+            driver.queue.write_buffer(primitive_buffer, 0, bytemuck::cast_slice(&words));
+            driver.queue.write_buffer(vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+
+            let mut encoder = driver.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("Barrier primitives") });
+            cpass.set_pipeline(&self.barrier_primitives);
+            cpass.set_bind_group(0, bind_group, &[]);
+            cpass.set_bind_group(1, &self.barrier_bg, &[]);
+            cpass.set_bind_group(2, &self.dimension_bg, &[]);
+            cpass.dispatch_workgroups(max_width.div_ceil(16), max_height.div_ceil(16), encoded.len() as u32);
+            }
+            driver.queue.submit(Some(encoder.finish()));
+        }
     }
 
-    pub fn curl_barrier(&mut self, driver : &Driver){
-        let line = Line::new(((4 * self.x/10) as isize, (self.y/4) as isize), ((4 * self.x/10) as isize, (self.y/2) as isize), self.x as isize, self.y as isize);
-        self.draw_shape(driver, &line.unwrap());
+    fn reserve_primitive_buffers(&mut self, driver: &Driver, primitive_bytes: usize, vertex_bytes: usize){
+        let (current_primitives, current_vertices) = match &self.primitive_buffers {
+            Some((primitives, vertices, _)) => (primitives.size() as usize, vertices.size() as usize),
+            None => (0, 0),
+        };
+        if current_primitives >= primitive_bytes && current_vertices >= vertex_bytes{
+            return;
+        }
+        let create = |size: usize| driver.device.create_buffer(&wgpu::BufferDescriptor{
+            label: None,
+            size: size.next_power_of_two() as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let primitives = create(primitive_bytes.max(current_primitives));
+        let vertices = create(vertex_bytes.max(current_vertices));
+        let bind_group = driver.device.create_bind_group(&BindGroupDescriptor{
+            label: None,
+            layout: &self.primitive_bgl,
+            entries: &[BindGroupEntry{
+                binding: 0,
+                resource: primitives.as_entire_binding(),
+            },
+            BindGroupEntry{
+                binding: 1,
+                resource: vertices.as_entire_binding(),
+            }]
+        });
+        self.primitive_buffers = Some((primitives, vertices, bind_group));
     }
 
-    pub fn chaos_barrier(&mut self, driver : &Driver){
-        
-        let l1 = Line::new(((self.x/2) as isize, (9 * self.y/20) as isize), ((self.x/2) as isize, 0), self.x as isize, self.y as isize).unwrap();
-        self.draw_shape(driver, &l1);
-
-        let l2 = Line::new(((self.x/2) as isize, (11 * self.y/20) as isize), ((self.x/2) as isize, self.y as isize - 1), self.x as isize, self.y as isize).unwrap();
-        self.draw_shape(driver, &l2);
-
-        let c1 = Line::new(((3 * self.x/5) as isize, (self.y/2) as isize), ((3 * self.x/4) as isize, 3 * self.y as isize/4 ), self.x as isize, self.y as isize).unwrap();
-        self.draw_shape(driver, &c1);
-
-        let c2 = Line::new(((3 * self.x/5) as isize, (self.y/2) as isize), ((3 * self.x/4) as isize, self.y as isize/4), self.x as isize, self.y as isize).unwrap();
-        self.draw_shape(driver, &c2);
-
+    pub fn update_omega_buffer(&mut self,  driver : &Driver, omega: f32){
+        driver.queue.write_buffer(&self.omega_buffer, 0, bytemuck::bytes_of(&omega));
     }
 
-    pub fn welcome_barrier(&mut self, driver : &Driver){
-        let curried_draw = |p1: (isize, isize), p2: (isize, isize)| {
-            Line::new(p1, p2, self.x as isize, self.y as isize).unwrap()
-        };
-
-        let mut blob = Blob::new_empty();
-        let height = -1 * (self.y/4) as isize;
-        let bottom = (self.y/2) as isize;
-        let space = (self.x/50) as isize;
-        let mut current_x = (self.x/5) as isize;
-        let letter_width = (self.x/13) as isize;
-        // Draw W
-        let v1 = curried_draw((current_x, bottom + height), (current_x, bottom));
-        let d1 = curried_draw((current_x, bottom), (current_x + letter_width/2, bottom + height/2));
-        current_x += letter_width/2;
-        let d2 = curried_draw((current_x, bottom + height/2), (current_x + letter_width/2, bottom));
-        current_x += letter_width/2 ;
-        let v2 = curried_draw((current_x, bottom + height), (current_x, bottom));
-        current_x += space;
-        blob.join(&v1);
-        blob.join(&d1);
-        blob.join(&d2);
-        blob.join(&v2);
-
-        //Draw e
-        let v1 = curried_draw((current_x, bottom + height/2), (current_x, bottom));
-        let h1 = curried_draw((current_x, bottom), (current_x + letter_width, bottom));
-        let h2 = curried_draw((current_x, bottom + height/4), (current_x + letter_width, bottom + height/4));
-        let h3 = curried_draw((current_x, bottom + height/2), (current_x + letter_width, bottom + height/2));
-        let v2 = curried_draw((current_x + letter_width, bottom + height/2), (current_x + letter_width, bottom + height/4));
-        current_x += letter_width + space;
-        blob.join(&v1);
-        blob.join(&h1);
-        blob.join(&h2);
-        blob.join(&v2);
-        blob.join(&h3);
-
-        //Draw l
-        blob.join(&curried_draw((current_x, bottom), (current_x, bottom + height)));
-        current_x += space;
-
-        //Draw c
-        let v1 = curried_draw((current_x, bottom + height/2), (current_x, bottom));
-        let h1 = curried_draw((current_x, bottom), (current_x + letter_width, bottom));
-        let h2 = curried_draw((current_x, bottom + height/2), (current_x + letter_width, bottom + height/2));
-        blob.join(&v1);
-        blob.join(&h1);
-        blob.join(&h2);
-        current_x += letter_width + space;
-
-        //Draw o
-        let v1 = curried_draw((current_x, bottom + height/2), (current_x, bottom));
-        let h1 = curried_draw((current_x, bottom), (current_x + letter_width, bottom));
-        let h2 = curried_draw((current_x, bottom + height/2), (current_x + letter_width, bottom + height/2));
-        let v2 = curried_draw((current_x + letter_width, bottom + height/2), (current_x + letter_width, bottom));
-        blob.join(&v1);
-        blob.join(&h1);
-        blob.join(&h2);
-        blob.join(&v2);
-        current_x += letter_width + space;
-
-        //Draw m
-        let v1 = curried_draw((current_x, bottom + height/2), (current_x, bottom));
-        let v3 = curried_draw((current_x + letter_width/2, bottom), (current_x + letter_width/2, bottom + height/2));
-        let h1 = curried_draw((current_x, bottom + height/2), (current_x + letter_width, bottom + height/2));
-        let v2 = curried_draw((current_x + letter_width, bottom + height/2), (current_x + letter_width, bottom));
-        blob.join(&v1);
-        blob.join(&h1);
-        blob.join(&v2);
-        blob.join(&v3);
-        current_x += letter_width + space;
-
-        //Draw e
-        let v1 = curried_draw((current_x, bottom + height/2), (current_x, bottom));
-        let h1 = curried_draw((current_x, bottom), (current_x + letter_width, bottom));
-        let h2 = curried_draw((current_x, bottom + height/4), (current_x + letter_width, bottom + height/4));
-        let h3 = curried_draw((current_x, bottom + height/2), (current_x + letter_width, bottom + height/2));
-        let v2 = curried_draw((current_x + letter_width, bottom + height/2), (current_x + letter_width, bottom + height/4));
-        current_x += letter_width + space;
-        blob.join(&v1);
-        blob.join(&h1);
-        blob.join(&h2);
-        blob.join(&v2);
-        blob.join(&h3);
-
-        //Draw exclamation
-        let v1 = curried_draw((current_x, height/10 + bottom), (current_x, bottom));
-        let v2 = curried_draw((current_x, height/5 + bottom), (current_x, bottom + height));
-        blob.join(&v1);
-        blob.join(&v2);
-        self.draw_shape(driver, &blob);
-
+    pub fn reset_barrier(&mut self, driver : &Driver){
+        let barrier_reset = Self::init_barrier(self.x, self.y);
+        driver.queue.write_buffer(&self.barrier_buffer, 0, bytemuck::cast_slice(&barrier_reset));
     }
 
     fn set_single_cell(&self, index: usize)-> Vec<Vec<f32>>{
@@ -1502,6 +2263,7 @@ impl LBM{
     pub fn single_cell(&mut self, driver : &Driver, index: usize){
 
         let data = self.set_single_cell(index);
+        self.flow_speed = 0.0;
 
         for i in 0..9{
             driver.queue.write_buffer(&self.data_buffers[0][i], 0, bytemuck::cast_slice(&data[i]));
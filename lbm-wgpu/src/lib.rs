@@ -1,12 +1,19 @@
-use barrier_shapes::{Shape, blob::Blob, line, curve::Curve, curve_collection::CurveCollection};
+use annotation::{AnnotationSettings, AnnotationValues};
+use barrier_shapes::{Shape, blob::Blob, mask::Mask, line, brush::{Brush, BrushShape}, curve::{Curve, StrokeSmoothing}, curve_collection::CurveCollection, airfoil::Airfoil, circle::Circle, ellipse::Ellipse, rectangle::Rectangle, image_mask::{ImageMask, ImageFit}, svg::{self, SvgMode}, text, flood_fill, transform::Symmetry, primitive::Primitive};
+use camera::Camera;
+use color_maps::ColorStop;
 use driver::Driver;
-use lbm::ColorMap;
+use lbm::{ColorMap, ColorRange, Visualization, GlyphColor, GlyphSettings};
+use history::History;
+use readback::RangeReadback;
+use recorder::{Recorder, RecordingFormat, RecordingSettings};
+use scene::{Scene, SceneShape, FluidSettings, Boundaries};
 use web_sys::console;
-use winit::{event_loop::{EventLoop, ControlFlow}, dpi::LogicalSize, event::{Event, WindowEvent, ElementState}, window::Window};
+use winit::{event_loop::{EventLoop, ControlFlow}, dpi::LogicalSize, event::{Event, WindowEvent, ElementState, MouseButton, MouseScrollDelta}, window::Window};
 use wasm_bindgen::prelude::*;
 
 use lazy_static::lazy_static; // 1.4.0
-use std::{sync::Mutex, collections::{HashSet, HashMap}, mem};
+use std::{sync::Mutex, collections::HashSet, mem};
 use crate::lbm::SummaryStat;
 
 lazy_static! {
@@ -14,15 +21,20 @@ lazy_static! {
     static ref BARRIER_CHANGE: Mutex<bool> = Mutex::new(false);
     static ref PAUSE: Mutex<bool> = Mutex::new(false);
     static ref OUTPUT_CHANGED: Mutex<bool> = Mutex::new(true);
+    static ref VISUALIZATION: Mutex<Visualization> = Mutex::new(Visualization::Heatmap);
     static ref COMPUTE_PER_RENDER: Mutex<u32> = Mutex::new(15);
     static ref VISCOSITY: Mutex<f32> = Mutex::new(0.1);
     static ref VISCOSITY_CHANGED: Mutex<bool> = Mutex::new(false);
     static ref CURRENT_COLOR_MAP: Mutex<ColorMap> = Mutex::new(ColorMap::Jet);
     static ref COLOR_CHANGED: Mutex<bool> = Mutex::new(false);
+    static ref COLOR_RANGE: Mutex<(ColorRange, f32, f32, f32)> = Mutex::new((ColorRange::MapDefault, 0.0, 0.0, 0.0));
+    static ref CUSTOM_COLOR_MAP: Mutex<Vec<ColorStop>> = Mutex::new(color_maps::stops(ColorMap::Grayscale).unwrap());
     static ref CLICK_TYPE: Mutex<ClickType> = Mutex::new(ClickType::Inactive);
     static ref CLICK_TYPE_CHANGED: Mutex<bool> = Mutex::new(true);
     static ref UNDO_CHANGED: Mutex<bool> = Mutex::new(false);
     static ref UNDO_COUNT: Mutex<usize> = Mutex::new(0);
+    static ref REDO_COUNT: Mutex<usize> = Mutex::new(0);
+    static ref HISTORY_DEPTH: Mutex<usize> = Mutex::new(200);
     static ref STEP_MODE: Mutex<bool> = Mutex::new(false);
     static ref STEP_TAKEN: Mutex<bool> = Mutex::new(false);
     static ref FLUID_PRESET_CHANGE: Mutex<bool> = Mutex::new(false);
@@ -30,13 +42,47 @@ lazy_static! {
     static ref FLUID_SPEED: Mutex<f32> = Mutex::new(0.1);
     static ref BARRIER_PRESET_CHANGE: Mutex<bool> = Mutex::new(false);
     static ref BARRIER_PRESET: Mutex<BarrierPreset> = Mutex::new(BarrierPreset::Tunnel);
+    static ref RECORDING_CHANGED: Mutex<bool> = Mutex::new(false);
+    static ref RECORDING_SETTINGS: Mutex<Option<RecordingSettings>> = Mutex::new(None);
+    static ref RECORDING_OUTPUT: Mutex<Option<Vec<u8>>> = Mutex::new(None);
+    static ref SHAPE_REQUESTS: Mutex<Vec<ShapeRequest>> = Mutex::new(Vec::new());
+    static ref DRAW_BRUSH: Mutex<Brush> = Mutex::new(Brush::default());
+    static ref ERASE_BRUSH: Mutex<Brush> = Mutex::new(Brush::new(30.0, BrushShape::Round));
+    static ref LINE_SNAPPING: Mutex<(u32, bool)> = Mutex::new((0, false));
+    static ref SYMMETRY: Mutex<Symmetry> = Mutex::new(Symmetry::Off);
+    static ref STROKE_SMOOTHING: Mutex<(StrokeSmoothing, f32)> = Mutex::new((StrokeSmoothing::Off, 0.5));
+    static ref SCENE_REQUEST: Mutex<Option<String>> = Mutex::new(None);
+    static ref SCENE_EXPORT_REQUESTED: Mutex<bool> = Mutex::new(false);
+    static ref SCENE_OUTPUT: Mutex<Option<String>> = Mutex::new(None);
+    static ref DELETE_SELECTED: Mutex<bool> = Mutex::new(false);
+    static ref SELECTION: Mutex<Option<Vec<i32>>> = Mutex::new(None);
+    static ref CAMERA_RESET: Mutex<bool> = Mutex::new(false);
+    static ref ANNOTATIONS: Mutex<AnnotationSettings> = Mutex::new(AnnotationSettings::default());
+    static ref GLYPHS: Mutex<Option<GlyphSettings>> = Mutex::new(None);
+    static ref GLYPHS_CHANGED: Mutex<bool> = Mutex::new(false);
 }
 
 pub mod driver;
 pub mod barrier_shapes;
 pub mod lbm;
+pub mod recorder;
+pub mod readback;
+pub mod history;
+pub mod scene;
+pub mod camera;
+pub mod color_maps;
+pub mod annotation;
 
 const OMEGA:f32 = 1.0/(0.5 + 0.3);
+//Largest share of the lattice a single flood fill may cover
+const FILL_LIMIT:f32 = 0.5;
+//Degrees the line tool's angles snap to
+const ANGLE_SNAP:f64 = 15.0;
+//Cells between a click and a shape that still count as clicking on it
+const SELECT_TOLERANCE:isize = 2;
+//Zoom factor per line the mouse wheel scrolls, pixel deltas count PIXELS_PER_LINE to a line
+const ZOOM_STEP:f64 = 1.1;
+const PIXELS_PER_LINE:f64 = 100.0;
 
 
 pub async fn run_wasm(event_loop: EventLoop<()>, window:Window, x:u32, y:u32, pixel_ratio: f32) {
@@ -46,7 +92,16 @@ pub async fn run_wasm(event_loop: EventLoop<()>, window:Window, x:u32, y:u32, pi
     let mut lbm = lbm::LBM::new(&driver, OMEGA, x, y);
     let mut pressed = false; 
     let mut click_handler = ClickHandler::new(x, y);
+    let mut recorder = Recorder::new(x, y);
     let mut current_position: (isize, isize) = (0,0);
+    //Cursor position in window cells, before the camera maps it onto the lattice
+    let mut window_position: (f64, f64) = (0.0, 0.0);
+    let mut panning = false;
+    let mut camera_changed = false;
+    let mut boundaries = Boundaries::default();
+    let mut range_readback = RangeReadback::default();
+    //Last automatic color range read back from the GPU, for the colorbar's labels
+    let mut auto_range: Option<(f32, f32)> = None;
  
     let swapchain_capabilities = driver.surface.get_capabilities(&driver.adapter);
     let swapchain_format = swapchain_capabilities.formats[0];
@@ -80,15 +135,20 @@ pub async fn run_wasm(event_loop: EventLoop<()>, window:Window, x:u32, y:u32, pi
             Event::WindowEvent { 
                 event: WindowEvent::CursorMoved{position, ..}, ..  
             } => {
-                let temp:(i32, i32) = position.to_logical::<i32>(pixel_ratio.into()).into();
-                current_position = click_handler.validate_click((temp.0 as isize, temp.1 as isize));
+                let temp:(f64, f64) = position.to_logical::<f64>(pixel_ratio.into()).into();
+                if panning{
+                    click_handler.camera.pan((temp.0 - window_position.0, temp.1 - window_position.1));
+                    camera_changed = true;
+                }
+                window_position = temp;
+                current_position = click_handler.validate_click(window_position);
                 if pressed{
                     click_handler.handle_movement(current_position);
                 }
             }
 
             Event::WindowEvent{
-                event: WindowEvent::MouseInput {state, ..}, ..
+                event: WindowEvent::MouseInput {state, button, ..}, ..
             } => {
                 let mut click_type_changed = CLICK_TYPE_CHANGED.lock().unwrap();
                 if *click_type_changed{
@@ -96,19 +156,82 @@ pub async fn run_wasm(event_loop: EventLoop<()>, window:Window, x:u32, y:u32, pi
                     click_handler.switch_click_type(*t);
                     *click_type_changed = false;
                 }
-                if state == ElementState::Released{
+                //Other buttons, or any drag while no tool is active, move the view instead of drawing
+                if button != MouseButton::Left || click_handler.current_type == ClickType::Inactive{
+                    panning = state == ElementState::Pressed;
+                } else if state == ElementState::Released{
                     pressed = false;
-                    click_handler.handle_release();
+                    click_handler.handle_release(current_position);
                 } else{
                     pressed = true;
                     click_handler.handle_click(current_position);
                 }
             }
 
+            Event::WindowEvent{
+                event: WindowEvent::MouseWheel {delta, ..}, ..
+            } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y as f64,
+                    MouseScrollDelta::PixelDelta(p) => p.y / PIXELS_PER_LINE,
+                };
+                click_handler.camera.zoom(ZOOM_STEP.powf(lines), window_position);
+                current_position = click_handler.validate_click(window_position);
+                camera_changed = true;
+            }
+
             Event::RedrawRequested(_) => {
 
+                for request in SHAPE_REQUESTS.lock().unwrap().drain(..){
+                    match request.build(x, y) {
+                        Ok(s) => click_handler.add_shape(s),
+                        Err(e) => console::error_1(&format!("Could not create barrier: {}", e).into()),
+                    }
+                }
+
+                let mut delete_selected = DELETE_SELECTED.lock().unwrap();
+                if *delete_selected{
+                    click_handler.delete_selected();
+                    *delete_selected = false;
+                }
+
+                if let Some(source) = SCENE_REQUEST.lock().unwrap().take(){
+                    match Scene::from_json(&source).and_then(|s| s.build_shapes(x, y).map(|shapes| (s, shapes))) {
+                        Ok((scene, shapes)) => {
+                            lbm.reset_barrier(&driver);
+                            boundaries = scene.boundaries;
+                            //The walls cover both rows reset_barrier fills, so they are the whole base
+                            let walls = boundaries.walls(x, y);
+                            lbm.draw_shape(&driver, &walls);
+                            click_handler.clear_barrier(&walls.mask);
+                            *UNDO_COUNT.lock().unwrap() = 0;
+                            for shape in shapes{
+                                click_handler.add_shape(shape);
+                            }
+                            apply_fluid_settings(scene.fluid);
+                        },
+                        Err(e) => console::error_1(&format!("Could not load scene: {}", e).into()),
+                    }
+                }
+
+                let mut export_requested = SCENE_EXPORT_REQUESTED.lock().unwrap();
+                if *export_requested{
+                    let fluid = FluidSettings{
+                        viscosity: Some(*VISCOSITY.lock().unwrap()),
+                        speed: Some(*FLUID_SPEED.lock().unwrap()),
+                        compute_per_render: Some(*COMPUTE_PER_RENDER.lock().unwrap()),
+                    };
+                    let scene = Scene::from_barrier(&click_handler.history.barrier(), fluid, boundaries, x, y);
+                    match scene.to_json() {
+                        Ok(json) => *SCENE_OUTPUT.lock().unwrap() = Some(json),
+                        Err(e) => console::error_1(&format!("Could not export scene: {}", e).into()),
+                    }
+                    *export_requested = false;
+                }
+
                 let paused = *PAUSE.lock().unwrap();
-                let mut barrier_redraw = !click_handler.current_blob.is_empty() || !click_handler.current_curve.is_empty();
+                let mut barrier_redraw = !click_handler.current_blob.is_empty() || !click_handler.current_curve.is_empty()
+                    || !click_handler.current_primitives.is_empty();
                 let mut output_changed = OUTPUT_CHANGED.lock().unwrap();
                 let mut color_changed = COLOR_CHANGED.lock().unwrap();
                 let mut fluid_preset_changed = FLUID_PRESET_CHANGE.lock().unwrap();
@@ -120,34 +243,47 @@ pub async fn run_wasm(event_loop: EventLoop<()>, window:Window, x:u32, y:u32, pi
                 if *barrier_preset_changed{
 
                     lbm.reset_barrier(&driver);
+                    boundaries = Boundaries::default();
+                    //Presets aren't undoable, what they draw becomes the base the history undoes back to
+                    let mut base = boundaries.walls(x, y).mask;
                     match *BARRIER_PRESET.lock().unwrap(){
-                        BarrierPreset::Welcome => lbm.welcome_barrier(&driver),
+                        BarrierPreset::Welcome => base.join_mask(&draw_preset(&mut lbm, &driver, scene::WELCOME)),
                         BarrierPreset::Tunnel => (),
-                        BarrierPreset::Curl => lbm.curl_barrier(&driver),
-                        BarrierPreset::Chaos => lbm.chaos_barrier(&driver),
+                        BarrierPreset::Curl => base.join_mask(&draw_preset(&mut lbm, &driver, scene::CURL)),
+                        BarrierPreset::Chaos => base.join_mask(&draw_preset(&mut lbm, &driver, scene::CHAOS)),
+                        BarrierPreset::SwitchingLines => base.join_mask(&draw_preset(&mut lbm, &driver, scene::SWITCHING_LINES)),
                     }
 
-                    click_handler.clear_barrier();
+                    click_handler.clear_barrier(&base);
                     barrier_redraw = false;
                     let mut undo_count = UNDO_COUNT.lock().unwrap();
                     *undo_count = 0;
+                    *REDO_COUNT.lock().unwrap() = 0;
                     *undo_changed = false;
                 }
 
                 if *undo_changed{
                     let mut undo_count = UNDO_COUNT.lock().unwrap();
-                    let mut undo_blob = Blob::new_empty();
+                    let mut redo_count = REDO_COUNT.lock().unwrap();
+                    let mut undo_blob = Mask::new_empty();
                     for _ in 0..*undo_count{
                         // click_handler.test_undo();
                         match click_handler.undo() {
-                            Some(u) => undo_blob.join(&*u),
+                            Some(u) => undo_blob.join_mask(&u),
                             None => {},
                         }
                     }
+                    for _ in 0..*redo_count{
+                        if let Some(r) = click_handler.redo(){
+                            undo_blob.join_mask(&r);
+                        }
+                    }
+                    *redo_count = 0;
                     if !undo_blob.is_empty(){
-                        lbm.draw_shape(&driver, &undo_blob);
+                        lbm.draw_mask(&driver, &undo_blob);
                     }
                     click_handler.empty_all();
+                    click_handler.publish_selection();
                     barrier_redraw = false;
                     *undo_count = 0;
                 }
@@ -166,18 +302,71 @@ pub async fn run_wasm(event_loop: EventLoop<()>, window:Window, x:u32, y:u32, pi
                 if *output_changed{
                     let current:SummaryStat =  *CURRENT_OUTPUT.lock().unwrap();
                     lbm.set_summary(current);
+                    lbm.set_visualization(&driver, *VISUALIZATION.lock().unwrap());
+                    auto_range = None;
                 }
 
                 if *color_changed{
-                    lbm.color_map = *CURRENT_COLOR_MAP.lock().unwrap();
+                    let color_map = *CURRENT_COLOR_MAP.lock().unwrap();
+                    let stops = color_maps::stops(color_map).unwrap_or_else(|| CUSTOM_COLOR_MAP.lock().unwrap().clone());
+                    lbm.set_color_map(&driver, color_map, &stops);
+                    let (range, low, high, clip) = *COLOR_RANGE.lock().unwrap();
+                    lbm.set_color_range(&driver, range, low, high, clip);
+                    auto_range = None;
                 }
 
                 if barrier_redraw{
-                    click_handler.current_blob.join(&click_handler.current_curve);
-                    lbm.draw_shape(&driver, &click_handler.current_blob);
+                    let primitives = click_handler.take_primitives();
+                    lbm.draw_mask(&driver, &click_handler.current_blob);
+                    draw_primitive_runs(&mut lbm, &driver, &primitives);
                     click_handler.update(pressed, current_position);
                 }
 
+                let (pending, outline) = click_handler.preview(pressed, current_position);
+                let overlay_changed = lbm.set_overlay(&driver, &pending, &outline);
+
+                let mut camera_reset = CAMERA_RESET.lock().unwrap();
+                if *camera_reset{
+                    click_handler.camera.reset();
+                    current_position = click_handler.validate_click(window_position);
+                    camera_changed = true;
+                    *camera_reset = false;
+                }
+                let camera_redraw = camera_changed;
+                if camera_changed{
+                    lbm.set_camera(&driver, &click_handler.camera);
+                    camera_changed = false;
+                }
+
+                let mut glyphs_changed = GLYPHS_CHANGED.lock().unwrap();
+                let glyph_redraw = *glyphs_changed;
+                if *glyphs_changed{
+                    lbm.set_glyphs(&driver, *GLYPHS.lock().unwrap());
+                    *glyphs_changed = false;
+                }
+
+                let annotations = *ANNOTATIONS.lock().unwrap();
+                let mut annotation_cells = Vec::<u32>::new();
+                if annotations.any(){
+                    let mut range = lbm.get_color_range();
+                    if range.is_none() && annotations.colorbar && lbm.shown_summary().is_some(){
+                        range_readback.request(&driver, &lbm);
+                        if let Some(r) = range_readback.poll(&driver){
+                            auto_range = Some(r);
+                        }
+                        range = auto_range;
+                    }
+                    //Channel Reynolds number, the lattice height being the length scale
+                    let values = AnnotationValues{
+                        summary_stat: lbm.shown_summary(),
+                        step_count: lbm.get_compute_num(),
+                        reynolds: lbm.get_flow_speed() * y as f32 / *VISCOSITY.lock().unwrap(),
+                        range,
+                    };
+                    annotation_cells = annotation::build(annotations, &values, x, y);
+                }
+                let annotation_changed = lbm.set_annotation(&driver, annotation_cells);
+
                 let mut viscosity_changed = VISCOSITY_CHANGED.lock().unwrap();
                 if *viscosity_changed{
                     let omega = 1.0/(3.0 * *VISCOSITY.lock().unwrap() + 0.5);
@@ -187,15 +376,33 @@ pub async fn run_wasm(event_loop: EventLoop<()>, window:Window, x:u32, y:u32, pi
 
                 if !paused && !step_mode{
                     let current:u32 =  *COMPUTE_PER_RENDER.lock().unwrap();
-                    lbm.iterate(&driver, current as usize);
+                    iterate_recording(&mut lbm, &driver, &mut recorder, current as usize);
                 }else if step_mode && *step{
                     if *step{
-                        lbm.iterate(&driver, 1);
+                        iterate_recording(&mut lbm, &driver, &mut recorder, 1);
                     }
                     *step = false;
-                }else if *output_changed || barrier_redraw || *color_changed || *fluid_preset_changed || *undo_changed || *barrier_preset_changed{
+                }else if *output_changed || barrier_redraw || overlay_changed || camera_redraw || glyph_redraw || annotation_changed || *color_changed || *fluid_preset_changed || *undo_changed || *barrier_preset_changed{
                     lbm.rerender(&driver);
                 }
+
+                let mut recording_changed = RECORDING_CHANGED.lock().unwrap();
+                if *recording_changed{
+                    match *RECORDING_SETTINGS.lock().unwrap() {
+                        Some(settings) => recorder.start(settings),
+                        //A recording that already hit its frame limit was stored when it stopped
+                        None => if let Some(output) = recorder.stop(){
+                            *RECORDING_OUTPUT.lock().unwrap() = Some(output);
+                        },
+                    }
+                    *recording_changed = false;
+                }
+                if recorder.update(&driver){
+                    if let Some(output) = recorder.stop(){
+                        *RECORDING_OUTPUT.lock().unwrap() = Some(output);
+                    }
+                }
+
                 *undo_changed = false;
                 *output_changed = false;
                 *color_changed = false;
@@ -221,17 +428,29 @@ pub enum ClickType{
     Line,
     Erase, 
     Draw, 
-    Inactive
+    Inactive,
+    Circle,
+    Ellipse,
+    Rectangle,
+    RotatedRectangle,
+    Polygon,
+    Fill,
+    Select,
 }
 
 struct ClickHandler{
     current_type: ClickType,
     line_points: Vec<(isize, isize)>,
-    current_blob: Blob,
+    shape_points: Vec<(isize, isize)>,
+    current_blob: Mask,
+    //Shapes the GPU rasterizes, drawn after current_blob with the value each writes
+    current_primitives: Vec<(Primitive, bool)>,
     current_curve: Curve,
     contiguous_curve: CurveCollection,
-    undo_stack: Vec<Box<dyn Shape>>,
-    history: HashMap<(isize, isize), Vec<bool>>,
+    history: History,
+    selected: Option<u32>,
+    drag_start: Option<(isize, isize)>,
+    camera: Camera,
     x: u32, 
     y: u32,
 }
@@ -239,26 +458,36 @@ struct ClickHandler{
 impl ClickHandler{
     
     pub fn new(x: u32, y:u32) -> ClickHandler{
+        let mut history = History::new(x, y, *HISTORY_DEPTH.lock().unwrap());
+        //The lattice starts out with the walls reset_barrier leaves
+        history.clear(&Boundaries::default().walls(x, y).mask);
         ClickHandler{
             current_type: ClickType::Draw,
-            current_blob: Blob { points: HashSet::<(isize, isize, bool)>::new() },
+            current_blob: Mask::new_empty(),
+            current_primitives: Vec::<(Primitive, bool)>::new(),
             line_points: Vec::<(isize, isize)>::new(),
-            undo_stack: Vec::<Box<dyn Shape>>::new(),
+            shape_points: Vec::<(isize, isize)>::new(),
             current_curve: Curve::new(),
             contiguous_curve: CurveCollection::new(),
-            history: HashMap::<(isize, isize), Vec<bool>>::new(),
+            history,
+            selected: None,
+            drag_start: None,
+            camera: Camera::new(x, y),
             x,
             y,
         }
     }
 
-    pub fn clear_barrier(&mut self){
+    //Base is the barrier left on the lattice by whatever cleared it, undo goes back to it but never past it
+    pub fn clear_barrier(&mut self, base: &Mask){
         self.current_curve = Curve::new();
         self.contiguous_curve = CurveCollection::new();
-        self.undo_stack.clear();
-        self.history.clear();
+        self.history.clear(base);
         self.current_blob.empty();
+        self.current_primitives.clear();
         self.line_points.clear();
+        self.shape_points.clear();
+        self.select(None);
     }
 
     pub fn handle_movement(&mut self, location: (isize, isize)){
@@ -269,15 +498,20 @@ impl ClickHandler{
         }  
     }
 
-    pub fn handle_release(&mut self){
+    pub fn handle_release(&mut self, location: (isize, isize)){
         match self.current_type {
             ClickType::Erase => self.release(),
             ClickType::Draw => self.release(),
+            ClickType::Circle | ClickType::Ellipse | ClickType::Rectangle => self.primitive_release(location),
+            ClickType::RotatedRectangle => self.rotated_rectangle_release(location),
+            ClickType::Select => self.select_release(location),
             _ => (),
         }
     }
 
     fn release(&mut self){
+        self.current_curve.finish(self.x as isize, self.y as isize);
+
         //Join current curve to blob so it will be rendered
         self.current_blob.join(&self.current_curve);
 
@@ -294,21 +528,60 @@ impl ClickHandler{
 
     pub fn empty_all(&mut self){
         self.current_blob.empty();
+        self.current_primitives.clear();
         self.current_curve.empty();
     }
 
+    //Everything waiting to reach the lattice as primitives, the current curve's cells join current_blob
+    //unless its round brush segments cover them
+    pub fn take_primitives(&mut self) -> Vec<(Primitive, bool)>{
+        let mut primitives = mem::take(&mut self.current_primitives);
+        let segments = self.current_curve.take_primitives();
+        if segments.is_empty(){
+            self.current_blob.join(&self.current_curve);
+        }
+        primitives.extend(segments);
+        primitives
+    }
+
     pub fn handle_click(&mut self, click_location: (isize, isize)){
         match self.current_type {
             ClickType::Line => self.line_click(click_location),
-            ClickType::Erase => self.current_curve.erase_segment(click_location, self.x as isize, self.y as isize),
-            ClickType::Draw => self.current_curve.add_segment(click_location, self.x as isize, self.y as isize),
+            ClickType::Circle | ClickType::Ellipse | ClickType::Rectangle => {
+                self.shape_points.clear();
+                self.shape_points.push(click_location);
+            },
+            ClickType::RotatedRectangle => self.rotated_rectangle_click(click_location),
+            ClickType::Polygon => self.polygon_click(click_location),
+            ClickType::Fill => self.fill(click_location),
+            ClickType::Select => {
+                self.select(self.history.pick(click_location, SELECT_TOLERANCE));
+                self.drag_start = Some(click_location);
+            },
+            ClickType::Erase => {
+                self.start_stroke();
+                self.current_curve.erase_segment(click_location, self.x as isize, self.y as isize)
+            },
+            ClickType::Draw => {
+                self.start_stroke();
+                self.current_curve.add_segment(click_location, self.x as isize, self.y as isize)
+            },
             _ => (),
         }
     }
 
+    fn start_stroke(&mut self){
+        let (smoothing, strength) = *STROKE_SMOOTHING.lock().unwrap();
+        self.current_curve.set_smoothing(smoothing, strength as f64);
+        self.current_curve.set_brush(self.brush(self.current_type == ClickType::Erase));
+        self.current_curve.set_symmetry(*SYMMETRY.lock().unwrap());
+    }
+
     pub fn switch_click_type(&mut self, click_type: ClickType){
         self.current_type = click_type;
         self.line_points.clear();
+        self.shape_points.clear();
+        self.select(None);
     }
 
     pub fn update(&mut self, pressed: bool, location: (isize, isize)){
@@ -321,13 +594,9 @@ impl ClickHandler{
         }
     }
 
-    pub fn undo(&mut self) -> Option<Box<dyn Shape>>{
-        if self.current_blob.is_empty() && self.current_curve.is_empty(){
-            match self.undo_stack.pop() {
-                Some(s) => 
-                return Some(self.remove_shape(&*s)),
-                None => return None,
-            };
+    pub fn undo(&mut self) -> Option<Mask>{
+        if self.current_blob.is_empty() && self.current_curve.is_empty() && self.current_primitives.is_empty(){
+            return self.history.undo();
         } else {
             self.current_blob.empty();
             self.current_curve.empty();
@@ -335,35 +604,259 @@ impl ClickHandler{
         None
     }
 
+    pub fn redo(&mut self) -> Option<Mask>{
+        if self.current_blob.is_empty() && self.current_curve.is_empty() && self.current_primitives.is_empty(){
+            return self.history.redo();
+        }
+        None
+    }
+
+    //Fills against the history's copy of the barrier, which already holds shapes that haven't been drawn yet
+    fn fill(&mut self, seed: (isize, isize)){
+        let limit = (FILL_LIMIT * (self.x * self.y) as f32) as usize;
+        match flood_fill::fill(|i| self.history.is_solid(i), seed, limit, self.x as isize, self.y as isize) {
+            Ok(region) => self.add_shape(Box::new(region)),
+            Err(e) => console::error_1(&format!("Could not fill region: {}", e).into()),
+        }
+    }
+
+    pub fn delete_selected(&mut self){
+        if let Some(mask) = self.selected.and_then(|id| self.history.delete(id)){
+            self.current_blob.join_mask(&mask);
+        }
+        self.select(None);
+    }
+
+    //Shares the selected shape's bounds with the page, the shape can disappear through undo
+    pub fn publish_selection(&self){
+        let bounds = self.selected.and_then(|id| self.history.bounds(id));
+        *SELECTION.lock().unwrap() = bounds.map(|b| b.iter().map(|v| *v as i32).collect());
+    }
+
+    fn select(&mut self, id: Option<u32>){
+        self.selected = id;
+        self.drag_start = None;
+        self.publish_selection();
+    }
+
+    //Dragging moves the shape by the distance between press and release, recorded in the history as one step
+    fn select_release(&mut self, location: (isize, isize)){
+        let (id, start) = match (self.selected, self.drag_start.take()) {
+            (Some(id), Some(start)) => (id, start),
+            _ => return,
+        };
+        if location != start{
+            if let Some(mask) = self.history.translate(id, (location.0 - start.0, location.1 - start.1)){
+                self.current_blob.join_mask(&mask);
+            }
+        }
+        self.publish_selection();
+    }
+
+    pub fn add_shape(&mut self, shape: Box<dyn Shape>){
+        self.current_blob.join(&*shape);
+        self.add_to_history(shape);
+    }
+
     fn line_click(&mut self, click_location: (isize, isize)){
-        self.line_points.push(click_location);
+        let point = self.line_point(click_location);
+        self.line_points.push(point);
         if self.line_points.len() >= 2{
-            let shape = line::Line::new(self.line_points[0], 
-                                              self.line_points[1], 
-                                                            self.x as isize, 
-                                                            self.y as isize)
-                                                            .unwrap();
-            self.current_blob.join(&shape);
-            self.add_to_history(Box::new(shape));
+            let brush = self.brush(false);
+            if brush.shape == BrushShape::Round{
+                self.add_primitive(brush.segment(self.line_points[0], self.line_points[1]));
+            } else {
+                let shape = line::Line::with_brush(self.line_points[0], 
+                                                  self.line_points[1], 
+                                                  &brush,
+                                                  true,
+                                                  self.x as isize, 
+                                                  self.y as isize);
+                self.current_blob.join(&shape);
+                self.add_to_history(Box::new(shape));
+            }
             self.line_points.clear();
         }
     }
 
+    fn line_point(&self, location: (isize, isize)) -> (isize, isize){
+        let (grid, snap_angles) = *LINE_SNAPPING.lock().unwrap();
+        let point = line::Line::snap_to_grid(location, grid, self.x as isize, self.y as isize);
+        match self.line_points.first() {
+            Some(start) if snap_angles => line::Line::snap_angle(*start, point, ANGLE_SNAP, self.x as isize, self.y as isize),
+            _ => point,
+        }
+    }
+
+    //Press sets the center or first corner, release sets the radius or opposite corner
+    fn primitive_release(&mut self, release_location: (isize, isize)){
+        let start = match self.shape_points.pop() {
+            Some(p) => p,
+            None => return,
+        };
+        self.shape_points.clear();
+        match self.gpu_primitive(start, release_location) {
+            Some(p) => self.add_primitive(p),
+            None => {
+                let shape = self.primitive(start, release_location);
+                self.add_shape(shape);
+            },
+        }
+    }
+
+    //Circles and rectangles are sent to the GPU, matching the cells Circle::new and Rectangle::new pick
+    fn gpu_primitive(&self, start: (isize, isize), end: (isize, isize)) -> Option<Primitive>{
+        let (min, max) = ((start.0.min(end.0) as f64, start.1.min(end.1) as f64), (start.0.max(end.0) as f64, start.1.max(end.1) as f64));
+        match self.current_type {
+            ClickType::Circle => Some(Primitive::Circle{
+                center: (start.0 as f64, start.1 as f64),
+                radius: (((end.0 - start.0).pow(2) + (end.1 - start.1).pow(2)) as f64).sqrt(),
+            }),
+            ClickType::Rectangle => Some(Primitive::Polygon{ vertices: vec![min, (max.0, min.1), max, (min.0, max.1)] }),
+            _ => None,
+        }
+    }
+
+    //Records the cells the GPU will set in the history and queues the primitive for the next frame
+    fn add_primitive(&mut self, primitive: Primitive){
+        self.add_to_history(Box::new(Blob::new(primitive.rasterize(true, self.x, self.y))));
+        self.current_primitives.push((primitive, true));
+    }
+
+    fn primitive(&self, start: (isize, isize), end: (isize, isize)) -> Box<dyn Shape>{
+        let (xdim, ydim) = (self.x as isize, self.y as isize);
+        match self.current_type {
+            ClickType::Circle => {
+                let radius = (((end.0 - start.0).pow(2) + (end.1 - start.1).pow(2)) as f64).sqrt();
+                Box::new(Circle::new(start, radius, xdim, ydim))
+            },
+            ClickType::Ellipse => Box::new(Ellipse::from_corners(start, end, xdim, ydim)),
+            _ => Box::new(Rectangle::new(start, end, xdim, ydim)),
+        }
+    }
+
+    //What the current tool would draw if the pointer were released or clicked at location, along with the
+    //outline of the brush under the pointer. Shown by the overlay pass, nothing here touches the barrier
+    pub fn preview(&self, pressed: bool, location: (isize, isize)) -> (Blob, Vec<(isize, isize)>){
+        let (xdim, ydim) = (self.x as isize, self.y as isize);
+        let mut pending = Blob::new_empty();
+        let thin = Brush::new(1.0, BrushShape::Round);
+        let mut outline = Vec::<(isize, isize)>::new();
+        match self.current_type {
+            ClickType::Line => {
+                let brush = self.brush(false);
+                let end = self.line_point(location);
+                if let Some(start) = self.line_points.first(){
+                    pending.join(&line::Line::with_brush(*start, end, &brush, true, xdim, ydim));
+                }
+                outline = Self::brush_outline(&brush, end);
+            },
+            ClickType::Draw => outline = Self::brush_outline(&self.brush(false), location),
+            ClickType::Erase => outline = Self::brush_outline(&self.brush(true), location),
+            ClickType::Circle | ClickType::Ellipse | ClickType::Rectangle if pressed => {
+                if let Some(start) = self.shape_points.first(){
+                    pending.join(&*self.primitive(*start, location));
+                }
+            },
+            ClickType::RotatedRectangle => match self.shape_points.len() {
+                1 if pressed => pending.join(&line::Line::with_brush(self.shape_points[0], location, &thin, true, xdim, ydim)),
+                2 => pending.join(&Rectangle::from_edge(self.shape_points[0], self.shape_points[1], location, xdim, ydim)),
+                _ => (),
+            },
+            ClickType::Polygon => {
+                let mut vertices = self.shape_points.clone();
+                if !vertices.is_empty(){
+                    vertices.push(location);
+                }
+                for edge in vertices.windows(2){
+                    pending.join(&line::Line::with_brush(edge[0], edge[1], &thin, true, xdim, ydim));
+                }
+            },
+            ClickType::Select if pressed => {
+                if let (Some(id), Some(start)) = (self.selected, self.drag_start){
+                    let (dx, dy) = (location.0 - start.0, location.1 - start.1);
+                    if let Some(cells) = self.history.cells(id){
+                        pending.join(&cells.translate(dx, dy, xdim, ydim));
+                    }
+                }
+            },
+            _ => (),
+        }
+        (pending, outline)
+    }
+
+    //The configured draw or erase brush, narrowed to fit the lattice
+    fn brush(&self, erasing: bool) -> Brush{
+        let brush = if erasing { *ERASE_BRUSH.lock().unwrap() } else { *DRAW_BRUSH.lock().unwrap() };
+        brush.fit(self.x as isize, self.y as isize)
+    }
+
+    fn brush_outline(brush: &Brush, center: (isize, isize)) -> Vec<(isize, isize)>{
+        let footprint: HashSet<(isize, isize)> = brush.offsets().into_iter().collect();
+        footprint.iter()
+            .filter(|c| [(1, 0), (-1, 0), (0, 1), (0, -1)].iter().any(|d| !footprint.contains(&(c.0 + d.0, c.1 + d.1))))
+            .map(|c| (center.0 + c.0, center.1 + c.1))
+            .collect()
+    }
+
+    //Press and release define one edge, the next click sets how far the rectangle extends from it
+    fn rotated_rectangle_click(&mut self, click_location: (isize, isize)){
+        if self.shape_points.len() < 2{
+            self.shape_points.clear();
+            self.shape_points.push(click_location);
+            return;
+        }
+        let shape = Rectangle::from_edge(self.shape_points[0], 
+                                               self.shape_points[1], 
+                                               click_location, 
+                                               self.x as isize, 
+                                               self.y as isize);
+        self.shape_points.clear();
+        self.add_shape(Box::new(shape));
+    }
+
+    fn rotated_rectangle_release(&mut self, release_location: (isize, isize)){
+        if self.shape_points.len() == 1{
+            if self.shape_points[0] == release_location{
+                self.shape_points.clear();
+            } else {
+                self.shape_points.push(release_location);
+            }
+        }
+    }
+
+    //Each click adds a vertex, clicking back near the first vertex closes and fills the polygon
+    fn polygon_click(&mut self, click_location: (isize, isize)){
+        if let Some(first) = self.shape_points.first(){
+            let closing = (first.0 - click_location.0).abs() <= 3 && (first.1 - click_location.1).abs() <= 3;
+            if closing && self.shape_points.len() >= 3{
+                let vertices = self.shape_points.iter().map(|v| (v.0 as f64, v.1 as f64)).collect();
+                self.shape_points.clear();
+                self.add_primitive(Primitive::Polygon{ vertices });
+                return;
+            }
+        }
+        self.shape_points.push(click_location);
+    }
+
     fn draw_update(&mut self, click_location: (isize, isize)){
-        let mut temp = Curve::new();
+        let mut temp = self.current_curve.continuation();
         mem::swap(&mut self.current_curve, &mut temp);
         self.contiguous_curve.add_curve(temp);
         self.current_curve.add_segment(click_location, self.x as isize, self.y as isize);
     }
 
     fn erase_update(&mut self, click_location: (isize, isize)){
-        let mut temp = Curve::new();
+        let mut temp = self.current_curve.continuation();
         mem::swap(&mut self.current_curve, &mut temp);
         self.contiguous_curve.add_curve(temp);
-        self.current_curve.erase_segment(click_location, self.x as isize, self.x as isize);
+        self.current_curve.erase_segment(click_location, self.x as isize, self.y as isize);
     }
 
-    pub fn validate_click(&self, click_location: (isize, isize)) -> (isize, isize){
+    //Maps a window position through the camera to the lattice cell under it
+    pub fn validate_click(&self, window_location: (f64, f64)) -> (isize, isize){
+        let lattice_location = self.camera.to_lattice(window_location);
+        let click_location = (lattice_location.0.floor() as isize, lattice_location.1.floor() as isize);
         let mut new_click = (0,0);
         if click_location.0 < 0{
             new_click.0 = self.x as isize + click_location.0;
@@ -383,44 +876,8 @@ impl ClickHandler{
     }
 
     fn add_to_history(&mut self, shape: Box<dyn Shape>){
-        for i in shape.get_points(){
-            match self.history.get_mut(&(i.0, i.1)){
-                Some(vec) => {vec.push(i.2)},
-                None => {
-                    self.history.insert((i.0, i.1), vec![i.2]);
-                },
-            };
-        }
-        self.undo_stack.push(shape);
-    }
-
-    fn remove_shape(&mut self, shape: &dyn Shape) -> Box<dyn Shape>{
-        let mut points = Vec::<(isize, isize, bool)>::new();
-        for i in shape.get_points(){
-            match self.history.get_mut(&(i.0, i.1)){
-                Some(vec) => {
-                    vec.pop(); 
-                    points.push((i.0, i.1, Self::add_point(vec)));
-                    if vec.is_empty(){
-                        self.history.remove(&(i.0, i.1));
-                    }
-                },
-                None => {
-                    points.push((i.0, i.1, false));
-                },
-            };
-        }
-        points.sort();
-        let mut blob = Blob::new(HashSet::new());
-        blob.add(&points, self.x, self.y);
-        Box::new(blob)
-    }
-
-    fn add_point(vec: &Vec<bool>) -> bool{
-       if vec.is_empty(){
-        return false;
-       }
-       vec.last().unwrap().to_owned()
+        self.history.set_depth(*HISTORY_DEPTH.lock().unwrap());
+        self.history.push(&*shape);
     }
 }
 
@@ -439,9 +896,168 @@ pub enum BarrierPreset{
     Welcome, 
     Tunnel,
     Curl, 
-    Chaos
+    Chaos,
+    SwitchingLines,
 }
 
+//Presets skip the undo history, so shapes the GPU can rasterize are sent as primitives. Runs of primitives
+//with the same value are drawn together and everything else is drawn in order on the CPU. Returns the cells
+//drawn, for the history's base
+fn draw_preset(lbm: &mut lbm::LBM, driver: &Driver, source: &str) -> Mask{
+    let (x, y) = lbm.get_dimensions();
+    let scene = match Scene::from_json(source) {
+        Ok(s) => s,
+        Err(e) => {
+            console::error_1(&format!("Could not draw preset: {}", e).into());
+            return Mask::new_empty();
+        },
+    };
+    let walls = scene.boundaries.walls(x, y);
+    lbm.draw_shape(driver, &walls);
+    let mut drawn = walls.mask;
+    let mut batch = Vec::<Primitive>::new();
+    let mut batch_value = true;
+    for group in scene.groups(){
+        let shape = &group[0];
+        //Shapes combined with set operations are only known cell by cell
+        let primitive = if group.len() == 1 { shape.kind.primitive(x, y) } else { None };
+        if !batch.is_empty() && (primitive.is_none() || batch_value == shape.erase){
+            lbm.draw_primitives(driver, &batch, batch_value);
+            batch.clear();
+        }
+        match primitive {
+            Some(p) => {
+                batch_value = !shape.erase;
+                drawn.join_mask(&p.rasterize(batch_value, x, y));
+                batch.push(p);
+            },
+            None => match SceneShape::build_group(group, x, y) {
+                Ok(s) => {
+                    lbm.draw_shape(driver, &*s);
+                    drawn.join(&*s);
+                },
+                Err(e) => console::error_1(&format!("Could not draw preset: {}", e).into()),
+            },
+        }
+    }
+    lbm.draw_primitives(driver, &batch, batch_value);
+    drawn
+}
+
+//Stops at every step the recorder wants a frame at on the way, so frames are exactly its step count apart
+//however many steps run per rendered frame
+fn iterate_recording(lbm: &mut lbm::LBM, driver: &Driver, recorder: &mut Recorder, steps: usize){
+    let mut remaining = steps;
+    while let Some(until) = recorder.steps_until_capture(lbm.get_compute_num()).filter(|s| *s <= remaining){
+        lbm.advance(driver, until);
+        lbm.update_colors(driver);
+        recorder.capture(driver, lbm);
+        remaining -= until;
+    }
+    lbm.iterate(driver, remaining);
+}
+
+//Consecutive primitives writing the same value go to the GPU together, so draws and erases keep their order
+fn draw_primitive_runs(lbm: &mut lbm::LBM, driver: &Driver, primitives: &[(Primitive, bool)]){
+    for run in primitives.chunk_by(|a, b| a.1 == b.1){
+        let batch: Vec<Primitive> = run.iter().map(|p| p.0.clone()).collect();
+        lbm.draw_primitives(driver, &batch, run[0].1);
+    }
+}
+
+//Queues the same updates the individual WASMInteraction setters would
+fn apply_fluid_settings(fluid: FluidSettings){
+    if let Some(viscosity) = fluid.viscosity{
+        *VISCOSITY.lock().unwrap() = viscosity;
+        *VISCOSITY_CHANGED.lock().unwrap() = true;
+    }
+    if let Some(speed) = fluid.speed{
+        *FLUID_SPEED.lock().unwrap() = speed;
+        *FLUID_PRESET.lock().unwrap() = FluidPreset::CustomSpeed;
+        *FLUID_PRESET_CHANGE.lock().unwrap() = true;
+    }
+    if let Some(rate) = fluid.compute_per_render{
+        *COMPUTE_PER_RENDER.lock().unwrap() = rate;
+    }
+}
+
+
+//Shapes queued from javascript, built and committed to the undo stack on the next frame
+enum ShapeRequest{
+    Image{
+        bytes: Vec<u8>,
+        threshold: f32,
+        invert: bool,
+        fit: ImageFit,
+    },
+    Svg{
+        source: String,
+        mode: SvgMode,
+        fit: ImageFit,
+    },
+    Naca{
+        code: String,
+        placement: AirfoilPlacement,
+    },
+    Selig{
+        source: String,
+        placement: AirfoilPlacement,
+    },
+    Text{
+        text: String,
+        size: f32,
+        x: f32,
+        y: f32,
+        rotation: f32,
+        thickness: f32,
+    },
+    Line{
+        start: (f32, f32),
+        end: (f32, f32),
+    },
+}
+
+//Chord and leading edge are fractions of the lattice width and height
+struct AirfoilPlacement{
+    chord: f32,
+    x: f32,
+    y: f32,
+    angle_of_attack: f32,
+}
+
+impl AirfoilPlacement{
+    fn to_lattice(&self, x: u32, y: u32) -> (f64, (f64, f64), f64){
+        (self.chord as f64 * x as f64, (self.x as f64 * x as f64, self.y as f64 * y as f64), self.angle_of_attack as f64)
+    }
+}
+
+impl ShapeRequest{
+    fn build(self, x: u32, y: u32) -> Result<Box<dyn Shape>, String>{
+        let (xdim, ydim) = (x as isize, y as isize);
+        Ok(match self {
+            ShapeRequest::Image { bytes, threshold, invert, fit } =>
+                Box::new(ImageMask::new(&bytes, threshold, invert, fit, xdim, ydim)?),
+            ShapeRequest::Svg { source, mode, fit } =>
+                Box::new(svg::parse(&source, mode, fit, xdim, ydim)?),
+            ShapeRequest::Naca { code, placement } => {
+                let (chord, leading_edge, angle) = placement.to_lattice(x, y);
+                Box::new(Airfoil::naca(&code, chord, leading_edge, angle, xdim, ydim)?)
+            },
+            ShapeRequest::Selig { source, placement } => {
+                let (chord, leading_edge, angle) = placement.to_lattice(x, y);
+                Box::new(Airfoil::from_selig(&source, chord, leading_edge, angle, xdim, ydim)?)
+            },
+            ShapeRequest::Text { text, size, x: left, y: top, rotation, thickness } => {
+                let position = (left as f64 * x as f64, top as f64 * y as f64);
+                Box::new(text::render(&text, size as f64 * x as f64, position, rotation as f64, thickness as f64, xdim, ydim))
+            },
+            ShapeRequest::Line { start, end } => {
+                let cell = |p: (f32, f32)| (p.0.round() as isize, p.1.round() as isize);
+                Box::new(line::Line::with_brush(cell(start), cell(end), &DRAW_BRUSH.lock().unwrap().fit(xdim, ydim), true, xdim, ydim))
+            },
+        })
+    }
+}
 
 #[wasm_bindgen]
 struct WASMInteraction{
@@ -457,6 +1073,13 @@ impl WASMInteraction {
         *mutex_changer = true;
     }
 
+    pub fn set_visualization(visualization: Visualization){
+        let mut mutex_changer = VISUALIZATION.lock().unwrap();
+        *mutex_changer = visualization;
+        let mut mutex_changer = OUTPUT_CHANGED.lock().unwrap();
+        *mutex_changer = true;
+    }
+
     pub fn set_draw_type(draw_type: ClickType){
         let mut mutex_changer = CLICK_TYPE.lock().unwrap();
         *mutex_changer = draw_type;
@@ -471,6 +1094,62 @@ impl WASMInteraction {
         *mutex_changer = true;
     }
 
+    //Switches to a gradient through the given stops, flattened as position, red, green, blue with each from 0 to 1
+    pub fn set_custom_color_map(stops: Vec<f32>){
+        match color_maps::parse_stops(&stops) {
+            Ok(stops) => *CUSTOM_COLOR_MAP.lock().unwrap() = stops,
+            Err(e) => {
+                console::error_1(&format!("Could not set color map: {}", e).into());
+                return;
+            },
+        }
+        Self::set_color_map(ColorMap::Custom);
+    }
+
+    //Low and high are used by Fixed, clip is the share of cells the automatic ranges leave out at each end
+    pub fn set_color_range(range: ColorRange, low: f32, high: f32, clip: f32){
+        if range == ColorRange::Fixed && (low >= high || !low.is_finite() || !high.is_finite()){
+            console::error_1(&format!("Could not set color range: {} to {} is not an increasing finite range", low, high).into());
+            return;
+        }
+        if !(0.0..0.5).contains(&clip){
+            console::error_1(&format!("Could not set color range: clip {} is outside 0 to 0.5", clip).into());
+            return;
+        }
+        let mut mutex_changer = COLOR_RANGE.lock().unwrap();
+        *mutex_changer = (range, low, high, clip);
+        let mut mutex_changer = COLOR_CHANGED.lock().unwrap();
+        *mutex_changer = true;
+    }
+
+    pub fn set_annotations(colorbar: bool, summary_stat: bool, step_count: bool, reynolds: bool){
+        let mut mutex_changer = ANNOTATIONS.lock().unwrap();
+        *mutex_changer = AnnotationSettings{ colorbar, summary_stat, step_count, reynolds };
+    }
+
+    //Arrows every spacing cells reaching across the spacing at reference_speed, in color (0xRRGGBB) or colored by
+    //speed through the color map
+    pub fn set_vector_glyphs(enabled: bool, spacing: u32, reference_speed: f32, coloring: GlyphColor, color: u32){
+        if enabled && spacing == 0{
+            console::error_1(&"Could not set vector glyphs: spacing must be at least one cell".into());
+            return;
+        }
+        if enabled && !(reference_speed > 0.0 && reference_speed.is_finite()){
+            console::error_1(&format!("Could not set vector glyphs: reference speed {} is not positive", reference_speed).into());
+            return;
+        }
+        let color = [(color >> 16 & 0xff) as f32/255.0, (color >> 8 & 0xff) as f32/255.0, (color & 0xff) as f32/255.0];
+        let mut mutex_changer = GLYPHS.lock().unwrap();
+        *mutex_changer = if enabled { Some(GlyphSettings{ spacing, reference_speed, coloring, color }) } else { None };
+        let mut mutex_changer = GLYPHS_CHANGED.lock().unwrap();
+        *mutex_changer = true;
+    }
+
+    pub fn reset_camera(){
+        let mut mutex_changer = CAMERA_RESET.lock().unwrap();
+        *mutex_changer = true;
+    }
+
     pub fn toggle_pause(){
         let mut mutex_changer = PAUSE.lock().unwrap();
         *mutex_changer = !*mutex_changer;
@@ -504,6 +1183,19 @@ impl WASMInteraction {
         *mutex_changer = true;
     }
 
+    pub fn redo(){
+        let mut mutex_changer = REDO_COUNT.lock().unwrap();
+        *mutex_changer += 1;
+        let mut mutex_changer = UNDO_CHANGED.lock().unwrap();
+        *mutex_changer = true;
+    }
+
+    //Number of shapes that can be undone, older shapes stay drawn but drop out of the history
+    pub fn set_history_depth(depth: usize){
+        let mut mutex_changer = HISTORY_DEPTH.lock().unwrap();
+        *mutex_changer = depth;
+    }
+
     pub fn set_step_mode(){
         let mut step_mode = STEP_MODE.lock().unwrap();
         *step_mode = true;
@@ -532,6 +1224,127 @@ impl WASMInteraction {
         let mut barrier_preset = BARRIER_PRESET.lock().unwrap();
         *barrier_preset = b;
     }
+
+    //Threshold is a luminance in [0, 1], pixels darker than it become barriers
+    pub fn import_barrier_image(bytes: Vec<u8>, threshold: f32, invert: bool, fit: ImageFit){
+        let mut requests = SHAPE_REQUESTS.lock().unwrap();
+        requests.push(ShapeRequest::Image{ bytes, threshold, invert, fit });
+    }
+
+    //The SVG viewBox (or the bounds of its shapes) is mapped onto the lattice
+    pub fn import_barrier_svg(source: String, mode: SvgMode, fit: ImageFit){
+        let mut requests = SHAPE_REQUESTS.lock().unwrap();
+        requests.push(ShapeRequest::Svg{ source, mode, fit });
+    }
+
+    //Chord and leading edge position are fractions of the lattice size, angle of attack is in degrees
+    pub fn place_naca_airfoil(code: String, chord: f32, x: f32, y: f32, angle_of_attack: f32){
+        let mut requests = SHAPE_REQUESTS.lock().unwrap();
+        requests.push(ShapeRequest::Naca{ code, placement: AirfoilPlacement{ chord, x, y, angle_of_attack } });
+    }
+
+    pub fn place_selig_airfoil(source: String, chord: f32, x: f32, y: f32, angle_of_attack: f32){
+        let mut requests = SHAPE_REQUESTS.lock().unwrap();
+        requests.push(ShapeRequest::Selig{ source, placement: AirfoilPlacement{ chord, x, y, angle_of_attack } });
+    }
+
+    //Width is in lattice cells, used by the draw and line tools. Brushes wider than half the lattice's shorter
+    //side are narrowed to it
+    pub fn set_draw_brush(width: f32, shape: BrushShape){
+        let mut mutex_changer = DRAW_BRUSH.lock().unwrap();
+        *mutex_changer = Brush::new(width as f64, shape);
+    }
+
+    pub fn set_erase_brush(width: f32, shape: BrushShape){
+        let mut mutex_changer = ERASE_BRUSH.lock().unwrap();
+        *mutex_changer = Brush::new(width as f64, shape);
+    }
+
+    //Strength runs from 0 (straight segments) to 1, smoothed strokes trail the pointer by one segment
+    pub fn set_stroke_smoothing(smoothing: StrokeSmoothing, strength: f32){
+        let mut mutex_changer = STROKE_SMOOTHING.lock().unwrap();
+        *mutex_changer = (smoothing, strength);
+    }
+
+    //Mirrors freehand drawing and erasing across the lattice centerlines, each stroke and its copies undo together
+    pub fn set_symmetry(symmetry: Symmetry){
+        let mut mutex_changer = SYMMETRY.lock().unwrap();
+        *mutex_changer = symmetry;
+    }
+
+    //Size is the cap height as a fraction of the lattice width, x and y place the top left corner as fractions
+    //of the lattice, rotation is in degrees clockwise and thickness is the pen width in cells
+    pub fn place_text(text: String, size: f32, x: f32, y: f32, rotation: f32, thickness: f32){
+        let mut requests = SHAPE_REQUESTS.lock().unwrap();
+        requests.push(ShapeRequest::Text{ text, size, x, y, rotation, thickness });
+    }
+
+    //Grid spacing in cells for line endpoints, 0 turns grid snapping off, angles snap to 15 degree steps
+    pub fn set_line_snapping(grid: u32, snap_angles: bool){
+        let mut mutex_changer = LINE_SNAPPING.lock().unwrap();
+        *mutex_changer = (grid, snap_angles);
+    }
+
+    //Endpoints in lattice cells, drawn with the draw brush
+    pub fn place_line(x1: f32, y1: f32, x2: f32, y2: f32){
+        let mut requests = SHAPE_REQUESTS.lock().unwrap();
+        requests.push(ShapeRequest::Line{ start: (x1, y1), end: (x2, y2) });
+    }
+
+    //Start and length in lattice cells, angle in degrees clockwise from the positive x axis
+    pub fn place_line_polar(x: f32, y: f32, length: f32, angle: f32){
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let mut requests = SHAPE_REQUESTS.lock().unwrap();
+        requests.push(ShapeRequest::Line{ start: (x, y), end: (x + length * cos, y + length * sin) });
+    }
+
+    pub fn start_recording(format: RecordingFormat, steps_per_frame: usize, frame_rate: u32, max_frames: usize){
+        let mut settings = RECORDING_SETTINGS.lock().unwrap();
+        *settings = Some(RecordingSettings{ format, steps_per_frame, frame_rate, max_frames });
+        let mut recording_changer = RECORDING_CHANGED.lock().unwrap();
+        *recording_changer = true;
+    }
+
+    pub fn stop_recording(){
+        let mut settings = RECORDING_SETTINGS.lock().unwrap();
+        *settings = None;
+        let mut recording_changer = RECORDING_CHANGED.lock().unwrap();
+        *recording_changer = true;
+    }
+
+    //Returns the encoded GIF/APNG once a recording has been stopped or hit its frame limit
+    pub fn take_recording() -> Option<Vec<u8>>{
+        let mut output = RECORDING_OUTPUT.lock().unwrap();
+        output.take()
+    }
+
+    //Replaces the current barrier with the scene's shapes and applies its fluid settings
+    pub fn load_scene(source: String){
+        let mut scene = SCENE_REQUEST.lock().unwrap();
+        *scene = Some(source);
+    }
+
+    //Serializes the drawn barrier history, collect the JSON with take_scene on a later frame
+    pub fn export_scene(){
+        let mut export_requested = SCENE_EXPORT_REQUESTED.lock().unwrap();
+        *export_requested = true;
+    }
+
+    pub fn take_scene() -> Option<String>{
+        let mut output = SCENE_OUTPUT.lock().unwrap();
+        output.take()
+    }
+
+    //Erases the shape picked with ClickType::Select, undo brings it back
+    pub fn delete_selected(){
+        let mut mutex_changer = DELETE_SELECTED.lock().unwrap();
+        *mutex_changer = true;
+    }
+
+    //[min x, min y, max x, max y] in lattice cells of the selected shape, None when nothing is selected
+    pub fn get_selection() -> Option<Vec<i32>>{
+        SELECTION.lock().unwrap().clone()
+    }
 }
 
 #[wasm_bindgen]
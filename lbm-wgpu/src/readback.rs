@@ -0,0 +1,103 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{driver::Driver, lbm::LBM};
+
+//Copies a GPU buffer back to the CPU, mapping is asynchronous so the data arrives a few frames later
+#[derive(Default)]
+pub(crate) struct Readback{
+    staging_buffer: Option<wgpu::Buffer>,
+    //Request the mapping belongs to, then Some(true) once the staging buffer is mapped and Some(false) if
+    //mapping failed. A discarded request's callback can still arrive, the generation lets it be ignored
+    mapped: Arc<Mutex<(u64, Option<bool>)>>,
+    generation: u64,
+    pending: bool,
+}
+
+impl Readback{
+
+    //Returns false if a previous request is still waiting on its data
+    pub(crate) fn request<F: FnOnce(&mut wgpu::CommandEncoder, &wgpu::Buffer)>(&mut self, driver: &Driver, label: &str, size: u64, copy: F) -> bool{
+        if self.pending{
+            return false;
+        }
+        let staging = self.staging_buffer.get_or_insert_with(|| driver.device.create_buffer(&wgpu::BufferDescriptor{
+            label: Some(label),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }));
+        let mut encoder = driver.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        copy(&mut encoder, staging);
+        driver.queue.submit(Some(encoder.finish()));
+
+        self.generation += 1;
+        let generation = self.generation;
+        *self.mapped.lock().unwrap() = (generation, None);
+        let mapped = self.mapped.clone();
+        staging.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            let mut mapped = mapped.lock().unwrap();
+            if mapped.0 == generation{
+                mapped.1 = Some(result.is_ok());
+            }
+        });
+        self.pending = true;
+        true
+    }
+
+    //Called once per frame, returns the data once it has been mapped
+    pub(crate) fn poll(&mut self, driver: &Driver) -> Option<Vec<u32>>{
+        if !self.pending{
+            return None;
+        }
+        driver.device.poll(wgpu::Maintain::Poll);
+        let mapped = self.mapped.lock().unwrap().1;
+        match mapped {
+            Some(true) => {
+                let staging = self.staging_buffer.as_ref().unwrap();
+                let data = {
+                    let view = staging.slice(..).get_mapped_range();
+                    bytemuck::cast_slice::<u8, u32>(&view).to_vec()
+                };
+                staging.unmap();
+                self.pending = false;
+                Some(data)
+            },
+            Some(false) => {
+                self.pending = false;
+                None
+            },
+            None => None,
+        }
+    }
+
+    pub(crate) fn is_pending(&self) -> bool{
+        self.pending
+    }
+
+    //Drops a request that is still waiting, unmapping cancels a mapping in flight
+    pub(crate) fn discard(&mut self){
+        if self.pending && self.mapped.lock().unwrap().1 != Some(false){
+            self.staging_buffer.as_ref().unwrap().unmap();
+        }
+        //Anything the cancelled mapping reports from here on belongs to an old generation
+        self.mapped.lock().unwrap().0 = 0;
+        self.pending = false;
+    }
+}
+
+//Copies the color map's current value range back to the CPU, needed when the GPU picks it automatically
+#[derive(Default)]
+pub struct RangeReadback(Readback);
+
+impl RangeReadback{
+
+    pub fn request(&mut self, driver: &Driver, lbm: &LBM) -> bool{
+        let size = (2 * std::mem::size_of::<f32>()) as u64;
+        self.0.request(driver, "Range readback", size, |encoder, staging| lbm.copy_color_range(encoder, staging))
+    }
+
+    //Low and high ends of the range once mapped
+    pub fn poll(&mut self, driver: &Driver) -> Option<(f32, f32)>{
+        self.0.poll(driver).map(|words| (f32::from_bits(words[0]), f32::from_bits(words[1])))
+    }
+}
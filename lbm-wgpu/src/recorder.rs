@@ -0,0 +1,219 @@
+use std::collections::VecDeque;
+use wasm_bindgen::prelude::*;
+
+use crate::{driver::Driver, lbm::LBM, readback::Readback};
+
+#[wasm_bindgen]
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum RecordingFormat{
+    Gif,
+    Apng,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RecordingSettings{
+    pub format: RecordingFormat,
+    pub steps_per_frame: usize,
+    pub frame_rate: u32,
+    pub max_frames: usize,
+}
+
+//Captures waiting on the GPU at once, several can fall within one rendered frame when few steps separate them
+const MAX_IN_FLIGHT: usize = 4;
+//APNG has to know how many frames there are before writing the first, so its frames are kept until the
+//recording stops. GIF frames go straight into the encoder
+const MAX_BUFFERED_BYTES: usize = 256 << 20;
+
+pub struct Recorder{
+    settings: RecordingSettings,
+    recording: bool,
+    gif: Option<gif::Encoder<Vec<u8>>>,
+    frames: Vec<Vec<u8>>,
+    frame_count: usize,
+    //Compute step the next capture is due at
+    next_capture: Option<usize>,
+    //Oldest capture first, so frames come out in order
+    in_flight: VecDeque<Readback>,
+    idle: Vec<Readback>,
+    x: u32,
+    y: u32,
+}
+
+impl Recorder{
+
+    pub fn new(x: u32, y: u32) -> Recorder{
+        Recorder{
+            settings: RecordingSettings{
+                format: RecordingFormat::Gif,
+                steps_per_frame: 15,
+                frame_rate: 30,
+                max_frames: 120,
+            },
+            recording: false,
+            gif: None,
+            frames: Vec::<Vec<u8>>::new(),
+            frame_count: 0,
+            next_capture: None,
+            in_flight: VecDeque::<Readback>::new(),
+            idle: Vec::<Readback>::new(),
+            x,
+            y,
+        }
+    }
+
+    pub fn start(&mut self, settings: RecordingSettings){
+        let frame_bytes = 4 * self.x as usize * self.y as usize;
+        let max_frames = match settings.format {
+            RecordingFormat::Gif => settings.max_frames,
+            RecordingFormat::Apng => settings.max_frames.min(MAX_BUFFERED_BYTES / frame_bytes.max(1)),
+        };
+        self.settings = RecordingSettings{
+            steps_per_frame: settings.steps_per_frame.max(1),
+            frame_rate: settings.frame_rate.clamp(1, 100),
+            max_frames: max_frames.max(1),
+            ..settings
+        };
+        self.discard_in_flight();
+        self.frames.clear();
+        self.frame_count = 0;
+        self.next_capture = None;
+        self.gif = match self.settings.format {
+            RecordingFormat::Gif => match self.gif_encoder() {
+                Ok(encoder) => Some(encoder),
+                Err(e) => {
+                    web_sys::console::error_1(&format!("Failed to start recording: {}", e).into());
+                    return;
+                }
+            },
+            RecordingFormat::Apng => None,
+        };
+        self.recording = true;
+    }
+
+    //Stops recording and finishes the encoding, returns None if nothing was captured
+    pub fn stop(&mut self) -> Option<Vec<u8>>{
+        if !self.recording{
+            return None;
+        }
+        self.recording = false;
+        self.discard_in_flight();
+        let frames = std::mem::take(&mut self.frames);
+        let gif = self.gif.take();
+        if self.frame_count == 0{
+            return None;
+        }
+        let encoded = match (self.settings.format, gif) {
+            (RecordingFormat::Gif, Some(encoder)) => encoder.into_inner().map_err(|e| e.to_string()),
+            (RecordingFormat::Gif, None) => Err("the encoder failed earlier".to_string()),
+            (RecordingFormat::Apng, _) => self.encode_apng(frames),
+        };
+        match encoded {
+            Ok(bytes) => Some(bytes),
+            Err(e) => {
+                web_sys::console::error_1(&format!("Failed to encode recording: {}", e).into());
+                None
+            }
+        }
+    }
+
+    //Compute steps left until the next frame is due, None when nothing more will be captured. The main loop
+    //stops the simulation there and calls capture
+    pub fn steps_until_capture(&self, step: usize) -> Option<usize>{
+        if !self.recording || self.frame_count + self.in_flight.len() >= self.settings.max_frames{
+            return None;
+        }
+        Some(match self.next_capture {
+            //The step count goes back to 0 when the fluid is reset
+            Some(next) if next <= step + self.settings.steps_per_frame => next.saturating_sub(step),
+            _ => 0,
+        })
+    }
+
+    //Copies the colors, which have to be up to date with the current step. Skips the frame if too many
+    //captures are still waiting on the GPU
+    pub fn capture(&mut self, driver: &Driver, lbm: &LBM){
+        let step = lbm.get_compute_num();
+        self.next_capture = Some(step + self.settings.steps_per_frame);
+        if self.in_flight.len() >= MAX_IN_FLIGHT{
+            return;
+        }
+        let mut readback = self.idle.pop().unwrap_or_default();
+        let size = (4 * self.x as usize * self.y as usize * std::mem::size_of::<f32>()) as u64;
+        if readback.request(driver, "Recorder staging", size, |encoder, staging| lbm.copy_colors(encoder, staging)){
+            self.in_flight.push_back(readback);
+        }
+    }
+
+    //Called once per rendered frame to collect captured frames, returns true once the frame limit has been reached
+    pub fn update(&mut self, driver: &Driver) -> bool{
+        if !self.recording{
+            return false;
+        }
+        while let Some(readback) = self.in_flight.front_mut(){
+            let words = readback.poll(driver);
+            if readback.is_pending(){
+                break;
+            }
+            if let Some(words) = words{
+                self.add_frame(Self::to_rgba(bytemuck::cast_slice(&words)));
+            }
+            let readback = self.in_flight.pop_front().unwrap();
+            self.idle.push(readback);
+        }
+        self.frame_count >= self.settings.max_frames
+    }
+
+    fn add_frame(&mut self, mut rgba: Vec<u8>){
+        self.frame_count += 1;
+        if self.settings.format == RecordingFormat::Apng{
+            return self.frames.push(rgba);
+        }
+        let (width, height) = (self.x as u16, self.y as u16);
+        let delay = (100 / self.settings.frame_rate).max(1) as u16;
+        if let Some(encoder) = self.gif.as_mut(){
+            let mut frame = gif::Frame::from_rgba_speed(width, height, &mut rgba, 10);
+            frame.delay = delay;
+            if let Err(e) = encoder.write_frame(&frame){
+                web_sys::console::error_1(&format!("Failed to encode recording: {}", e).into());
+                self.gif = None;
+            }
+        }
+    }
+
+    fn discard_in_flight(&mut self){
+        for mut readback in self.in_flight.drain(..){
+            readback.discard();
+            self.idle.push(readback);
+        }
+    }
+
+    fn to_rgba(colors: &[f32]) -> Vec<u8>{
+        let to_byte = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        colors.chunks_exact(4).flat_map(|c| [to_byte(c[0]), to_byte(c[1]), to_byte(c[2]), 255]).collect()
+    }
+
+    fn gif_encoder(&self) -> Result<gif::Encoder<Vec<u8>>, String>{
+        let width = u16::try_from(self.x).map_err(|e| e.to_string())?;
+        let height = u16::try_from(self.y).map_err(|e| e.to_string())?;
+        let mut encoder = gif::Encoder::new(Vec::<u8>::new(), width, height, &[]).map_err(|e| e.to_string())?;
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(|e| e.to_string())?;
+        Ok(encoder)
+    }
+
+    fn encode_apng(&self, frames: Vec<Vec<u8>>) -> Result<Vec<u8>, String>{
+        let mut bytes = Vec::<u8>::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, self.x, self.y);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_animated(frames.len() as u32, 0).map_err(|e| e.to_string())?;
+            encoder.set_frame_delay(1, self.settings.frame_rate as u16).map_err(|e| e.to_string())?;
+            let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+            for rgba in frames.iter(){
+                writer.write_image_data(rgba).map_err(|e| e.to_string())?;
+            }
+            writer.finish().map_err(|e| e.to_string())?;
+        }
+        Ok(bytes)
+    }
+}
@@ -0,0 +1,46 @@
+struct Dimensions{
+    row: u32,
+    col: u32,
+    total: u32,
+}
+
+struct VertexOutput{
+    @builtin(position) pos: vec4<f32>,
+    @location(0) @interpolate(flat) color: vec4<f32>,
+}
+
+//Window cells rather than lattice cells, so annotations stay put as the camera moves
+fn calc_index(i: u32) -> vec2<f32>{
+    return vec2<f32>(2.0*f32(i % dimensions.row)/f32(dimensions.row), -2.0*f32(i / dimensions.row)/f32(dimensions.col)) ;
+}
+
+//Each entry is a cell index and a packed RGBA color, or with the index's top bit set a color map position
+//from 0 to 65535, see annotation.rs
+@group(0) @binding(0) var<storage, read> cells: array<vec2<u32>>;
+@group(1) @binding(0) var<uniform> dimensions: Dimensions;
+@group(2) @binding(0) var<storage, read> lut: array<vec4<f32>, 256>;
+
+@vertex
+fn vs_main(@location(0) ver: vec2<f32>, @builtin(instance_index) ins: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let cell = cells[ins];
+    out.pos = vec4<f32>(calc_index(cell.x & 0x7fffffffu) + ver, 0.0, 1.0);
+    if ((cell.x & 0x80000000u) != 0u) {
+        let position = 255.0 * f32(cell.y)/65535.0;
+        let below = u32(floor(position));
+        let above = min(below + 1u, 255u);
+        out.color = vec4<f32>(mix(lut[below].rgb, lut[above].rgb, position - f32(below)), 1.0);
+    } else {
+        out.color = unpack4x8unorm(cell.y);
+    }
+    return out;
+}
+
+struct FragmentInput{
+    @location(0) @interpolate(flat) color: vec4<f32>,
+}
+
+@fragment
+fn fs_main(f: FragmentInput) -> @location(0) vec4<f32> {
+    return f.color;
+}
@@ -0,0 +1,32 @@
+//Values from low to high spread across the lookup table, see color_maps.rs
+struct Range{
+    low: f32,
+    high: f32,
+}
+
+@group(0) @binding(0) var<storage, read_write> colors: array<vec3<f32>>;
+
+@group(1) @binding(0) var<storage, read_write> value: array<f32>;
+
+@group(2) @binding(0) var<storage, read_write> barrier: array<u32>;
+
+@group(3) @binding(0) var<uniform> size: u32;
+@group(3) @binding(1) var<storage, read> lut: array<vec4<f32>, 256>;
+@group(3) @binding(2) var<uniform> range: Range;
+
+@compute
+@workgroup_size(256)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>){
+    if (global_invocation_id.x >= size) {
+        return;
+    }
+
+    let position = 255.0 * clamp((value[global_invocation_id.x] - range.low)/(range.high - range.low), 0.0, 1.0);
+    let below = u32(floor(position));
+    let above = min(below + 1u, 255u);
+    colors[global_invocation_id.x] = mix(lut[below].rgb, lut[above].rgb, position - f32(below));
+
+    if(barrier[global_invocation_id.x] == 1u){
+        colors[global_invocation_id.x] = vec3(0.0,0.0,0.0);
+    }
+}
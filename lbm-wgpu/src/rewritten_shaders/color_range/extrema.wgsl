@@ -0,0 +1,42 @@
+//Extremes are kept as order preserving keys so atomicMax finds them, low inverted so both start from zero
+struct Stats{
+    low: atomic<u32>,
+    high: atomic<u32>,
+    histogram: array<atomic<u32>, 256>,
+}
+
+@group(0) @binding(0) var<storage, read_write> value: array<f32>;
+
+@group(1) @binding(0) var<storage, read_write> barrier: array<u32>;
+
+@group(2) @binding(0) var<uniform> size: u32;
+
+@group(3) @binding(0) var<storage, read_write> stats: Stats;
+
+var<workgroup> low: atomic<u32>;
+var<workgroup> high: atomic<u32>;
+
+//Flips the bits of floats so they compare as unsigned integers in the same order
+fn key(v: f32) -> u32{
+    let bits = bitcast<u32>(v);
+    if ((bits & 0x80000000u) != 0u) {
+        return ~bits;
+    }
+    return bits | 0x80000000u;
+}
+
+@compute
+@workgroup_size(256)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>, @builtin(local_invocation_index) local_index: u32){
+    let i = global_invocation_id.x;
+    if (i < size && barrier[i] != 1u && value[i] == value[i]) {
+        let k = key(value[i]);
+        atomicMax(&low, ~k);
+        atomicMax(&high, k);
+    }
+    workgroupBarrier();
+    if (local_index == 0u) {
+        atomicMax(&stats.low, atomicLoad(&low));
+        atomicMax(&stats.high, atomicLoad(&high));
+    }
+}
@@ -0,0 +1,44 @@
+struct Stats{
+    low: atomic<u32>,
+    high: atomic<u32>,
+    histogram: array<atomic<u32>, 256>,
+}
+
+@group(0) @binding(0) var<storage, read_write> value: array<f32>;
+
+@group(1) @binding(0) var<storage, read_write> barrier: array<u32>;
+
+@group(2) @binding(0) var<uniform> size: u32;
+
+@group(3) @binding(0) var<storage, read_write> stats: Stats;
+
+var<workgroup> bins: array<atomic<u32>, 256>;
+
+//Inverse of key in extrema.wgsl
+fn from_key(k: u32) -> f32{
+    if ((k & 0x80000000u) != 0u) {
+        return bitcast<f32>(k & 0x7fffffffu);
+    }
+    return bitcast<f32>(~k);
+}
+
+//Counts the fluid cells falling in each of 256 equal bins between the extremes
+@compute
+@workgroup_size(256)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>, @builtin(local_invocation_index) local_index: u32){
+    let i = global_invocation_id.x;
+    if (i < size && barrier[i] != 1u && value[i] == value[i]) {
+        let low = from_key(~atomicLoad(&stats.low));
+        let high = from_key(atomicLoad(&stats.high));
+        var bin = 0u;
+        if (high > low) {
+            bin = min(u32(max(256.0 * (value[i] - low)/(high - low), 0.0)), 255u);
+        }
+        atomicAdd(&bins[bin], 1u);
+    }
+    workgroupBarrier();
+    let count = atomicLoad(&bins[local_index]);
+    if (count != 0u) {
+        atomicAdd(&stats.histogram[local_index], count);
+    }
+}
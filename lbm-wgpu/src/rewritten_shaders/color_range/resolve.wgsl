@@ -0,0 +1,78 @@
+struct Stats{
+    low: atomic<u32>,
+    high: atomic<u32>,
+    histogram: array<atomic<u32>, 256>,
+}
+
+//Mode 0 spans the values, 1 spans them symmetrically about zero. Clip is the share of cells left out at
+//each end so a few extreme cells don't wash out the rest
+struct Settings{
+    mode: u32,
+    clip: f32,
+}
+
+struct Range{
+    low: f32,
+    high: f32,
+}
+
+@group(3) @binding(0) var<storage, read_write> stats: Stats;
+@group(3) @binding(1) var<uniform> settings: Settings;
+@group(3) @binding(2) var<storage, read_write> range: Range;
+
+//Inverse of key in extrema.wgsl
+fn from_key(k: u32) -> f32{
+    if ((k & 0x80000000u) != 0u) {
+        return bitcast<f32>(k & 0x7fffffffu);
+    }
+    return bitcast<f32>(~k);
+}
+
+//Turns the histogram into the range the color map kernel reads, keeping the last range if no cell is fluid
+@compute
+@workgroup_size(1)
+fn main(){
+    var total = 0u;
+    for (var bin = 0u; bin < 256u; bin++) {
+        total += atomicLoad(&stats.histogram[bin]);
+    }
+    if (total == 0u) {
+        return;
+    }
+    let clipped = u32(settings.clip * f32(total));
+
+    var low_bin = 0u;
+    var counted = 0u;
+    for (var bin = 0u; bin < 256u; bin++) {
+        counted += atomicLoad(&stats.histogram[bin]);
+        if (counted > clipped) {
+            low_bin = bin;
+            break;
+        }
+    }
+    var high_bin = 255u;
+    counted = 0u;
+    for (var bin = 255i; bin >= 0i; bin--) {
+        counted += atomicLoad(&stats.histogram[bin]);
+        if (counted > clipped) {
+            high_bin = u32(bin);
+            break;
+        }
+    }
+
+    let low = from_key(~atomicLoad(&stats.low));
+    let width = (from_key(atomicLoad(&stats.high)) - low)/256.0;
+    var result = vec2<f32>(low + f32(low_bin) * width, low + f32(high_bin + 1u) * width);
+    if (settings.mode == 1u) {
+        let extent = max(abs(result.x), abs(result.y));
+        result = vec2<f32>(-extent, extent);
+    }
+    //A uniform field still needs a range to divide by
+    let center = (result.x + result.y)/2.0;
+    let least = 1e-6 * max(abs(center), 1.0);
+    if (result.y - result.x < least) {
+        result = center + vec2<f32>(-least, least)/2.0;
+    }
+    range.low = result.x;
+    range.high = result.y;
+}
@@ -0,0 +1,106 @@
+//Arrows every spacing cells, centered on the lattice. An arrow at reference speed spans the spacing, colored is
+//0 for the fixed color or 1 to color by speed through the color map, see GlyphSettings in lbm.rs
+struct Params{
+    row: u32,
+    col: u32,
+    spacing: u32,
+    colored: u32,
+    reference: f32,
+    color: vec4<f32>,
+}
+
+//Lattice position shown at the top left corner and how much cells are enlarged, see camera.rs
+struct Camera{
+    offset: vec2<f32>,
+    scale: f32,
+}
+
+struct VertexOutput{
+    @builtin(position) pos: vec4<f32>,
+    @location(0) @interpolate(flat) color: vec4<f32>,
+}
+
+@group(0) @binding(0) var<storage, read> ux: array<f32>;
+@group(0) @binding(1) var<storage, read> uy: array<f32>;
+@group(0) @binding(2) var<storage, read> rho: array<f32>;
+@group(0) @binding(3) var<storage, read> barrier: array<u32>;
+@group(1) @binding(0) var<uniform> params: Params;
+@group(2) @binding(0) var<uniform> camera: Camera;
+@group(3) @binding(0) var<storage, read> lut: array<vec4<f32>, 256>;
+
+//Shaft as two triangles then the head, along x from the tail at 0 to the tip at 1 with y across the arrow.
+//Shaft points are in shaft widths across, head points in head widths
+const SHAFT: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
+    vec2<f32>(0.0, -1.0), vec2<f32>(0.0, 1.0), vec2<f32>(1.0, 1.0),
+    vec2<f32>(0.0, -1.0), vec2<f32>(1.0, 1.0), vec2<f32>(1.0, -1.0),
+);
+const HEAD: array<vec2<f32>, 3> = array<vec2<f32>, 3>(
+    vec2<f32>(0.0, -1.0), vec2<f32>(0.0, 1.0), vec2<f32>(1.0, 0.0),
+);
+
+//Clip space position of a point in lattice cells
+fn to_clip(point: vec2<f32>) -> vec2<f32>{
+    let window = (point - camera.offset) * camera.scale;
+    return vec2<f32>(2.0 * window.x/f32(params.row) - 1.0, 1.0 - 2.0 * window.y/f32(params.col));
+}
+
+fn grid(length: u32) -> u32{
+    return max(length/params.spacing, 1u);
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) ver: u32, @builtin(instance_index) ins: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let slot = vec2<u32>(ins % grid(params.row), ins / grid(params.row));
+    let margin = (vec2<f32>(f32(params.row), f32(params.col)) - vec2<f32>(f32(grid(params.row)), f32(grid(params.col))) * f32(params.spacing))/2.0;
+    let center = max(margin, vec2<f32>(0.0, 0.0)) + (vec2<f32>(slot) + 0.5) * f32(params.spacing);
+    let cell = min(vec2<u32>(center), vec2<u32>(params.row - 1u, params.col - 1u));
+    let i = cell.x + cell.y * params.row;
+
+    //Momentum over density, with y flipped because north is up the lattice, towards lower rows
+    let velocity = vec2<f32>(ux[i], -uy[i])/rho[i];
+    let speed = length(velocity);
+    if (barrier[i] == 1u || speed < 1e-6) {
+        //Every vertex in the same place, so nothing is drawn
+        out.pos = vec4<f32>(2.0, 2.0, 0.0, 1.0);
+        return out;
+    }
+
+    let spacing = f32(params.spacing);
+    let arrow_length = min(speed/params.reference, 1.0) * 0.9 * spacing;
+    let head_length = min(0.4 * arrow_length, 0.3 * spacing);
+    let shaft_width = max(0.04 * spacing, 0.3);
+    let head_width = max(0.6 * head_length, 2.0 * shaft_width);
+
+    let along = velocity/speed;
+    let across = vec2<f32>(-along.y, along.x);
+    let tail = center - 0.5 * arrow_length * along;
+    //Copied to variables since constant arrays can only be indexed by constants
+    var shaft = SHAFT;
+    var head = HEAD;
+    var local: vec2<f32>;
+    if (ver < 6u) {
+        let p = shaft[ver];
+        local = vec2<f32>(p.x * (arrow_length - head_length), p.y * shaft_width);
+    } else {
+        let p = head[ver - 6u];
+        local = vec2<f32>(arrow_length - head_length + p.x * head_length, p.y * head_width);
+    }
+    out.pos = vec4<f32>(to_clip(tail + local.x * along + local.y * across), 0.0, 1.0);
+
+    if (params.colored == 1u) {
+        out.color = lut[u32(round(255.0 * min(speed/params.reference, 1.0)))];
+    } else {
+        out.color = params.color;
+    }
+    return out;
+}
+
+struct FragmentInput{
+    @location(0) @interpolate(flat) color: vec4<f32>,
+}
+
+@fragment
+fn fs_main(f: FragmentInput) -> @location(0) vec4<f32> {
+    return f.color;
+}
@@ -0,0 +1,56 @@
+struct Dimensions{
+    row: u32,
+    col: u32,
+    total: u32,
+}
+
+struct VertexOutput{
+    @builtin(position) pos: vec4<f32>,
+    @location(0) @interpolate(flat) kind: u32,
+}
+
+//Lattice position shown at the top left corner and how much cells are enlarged, see camera.rs
+struct Camera{
+    offset: vec2<f32>,
+    scale: f32,
+}
+
+//Clip space position of a vertex of cell i's quad, ver being that vertex of the top left cell's quad
+fn calc_index(i: u32, ver: vec2<f32>) -> vec2<f32>{
+    let cell = vec2<f32>(2.0*f32(i % dimensions.row)/f32(dimensions.row), -2.0*f32(i / dimensions.row)/f32(dimensions.col));
+    let offset = vec2<f32>(2.0*camera.offset.x/f32(dimensions.row), -2.0*camera.offset.y/f32(dimensions.col));
+    let corner = vec2<f32>(-1.0, 1.0);
+    return (cell - offset + ver - corner) * camera.scale + corner;
+}
+
+//Each entry is a cell index and what is shown there: 1 pending barrier, 2 pending erase, 3 brush outline
+@group(0) @binding(0) var<storage, read> cells: array<vec2<u32>>;
+@group(1) @binding(0) var<uniform> dimensions: Dimensions;
+@group(2) @binding(0) var<uniform> camera: Camera;
+
+@vertex
+fn vs_main(@location(0) ver: vec2<f32>, @builtin(instance_index) ins: u32) -> VertexOutput {
+    var out: VertexOutput;
+    out.pos = vec4<f32>(calc_index(cells[ins].x, ver), 0.0, 1.0);
+    out.kind = cells[ins].y;
+    return out;
+}
+
+struct FragmentInput{
+    @location(0) @interpolate(flat) kind: u32,
+}
+
+@fragment
+fn fs_main(f: FragmentInput) -> @location(0) vec4<f32> {
+    switch f.kind {
+        case 1u: {
+            return vec4<f32>(0.0, 0.0, 0.0, 0.6);
+        }
+        case 2u: {
+            return vec4<f32>(1.0, 1.0, 1.0, 0.4);
+        }
+        default: {
+            return vec4<f32>(1.0, 1.0, 1.0, 0.9);
+        }
+    }
+}
@@ -9,17 +9,28 @@ struct VertexOutput{
     @location(0) @interpolate(flat) instance_index: u32,
 }
 
-fn calc_index(i: u32) -> vec2<f32>{
-    return vec2<f32>(2.0*f32(i % dimensions.row)/f32(dimensions.row), -2.0*f32(i / dimensions.row)/f32(dimensions.col)) ;
+//Lattice position shown at the top left corner and how much cells are enlarged, see camera.rs
+struct Camera{
+    offset: vec2<f32>,
+    scale: f32,
+}
+
+//Clip space position of a vertex of cell i's quad, ver being that vertex of the top left cell's quad
+fn calc_index(i: u32, ver: vec2<f32>) -> vec2<f32>{
+    let cell = vec2<f32>(2.0*f32(i % dimensions.row)/f32(dimensions.row), -2.0*f32(i / dimensions.row)/f32(dimensions.col));
+    let offset = vec2<f32>(2.0*camera.offset.x/f32(dimensions.row), -2.0*camera.offset.y/f32(dimensions.col));
+    let corner = vec2<f32>(-1.0, 1.0);
+    return (cell - offset + ver - corner) * camera.scale + corner;
 }
 
 @group(0) @binding(0) var<storage, read_write> colors: array<vec3<f32>>;
 @group(1) @binding(0) var<uniform> dimensions: Dimensions;
+@group(2) @binding(0) var<uniform> camera: Camera;
 
 @vertex
 fn vs_main(@location(0) ver: vec2<f32>, @builtin(instance_index) ins: u32) -> VertexOutput {
     var out: VertexOutput;
-    out.pos = vec4<f32>(calc_index(ins) + ver, 0.0, 1.0);
+    out.pos = vec4<f32>(calc_index(ins, ver), 0.0, 1.0);
     out.instance_index = ins;
     return out;
 }
@@ -0,0 +1,119 @@
+struct Dimensions{
+    row: u32,
+    col: u32,
+    total: u32,
+}
+
+//kind is 0 for a segment from a to b, 1 for a circle around a and 2 for a polygon made of vertex_count vertices
+//starting at first_vertex. Each primitive is rasterized over its bounding box, which starts at (x, y). Points are
+//in half cells and every test is done on integers, exactly as Primitive::rasterize does it on the CPU
+struct Primitive{
+    kind: u32,
+    value: u32,
+    first_vertex: u32,
+    vertex_count: u32,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    ax: i32,
+    ay: i32,
+    bx: i32,
+    by: i32,
+    //Largest squared distance in half cells a covered cell can be from the segment, center or polygon edge
+    limit: u32,
+    pad_0: u32,
+    pad_1: u32,
+    pad_2: u32,
+}
+
+@group(0) @binding(0) var<storage, read> primitives: array<Primitive>;
+@group(0) @binding(1) var<storage, read> vertices: array<vec2<i32>>;
+
+@group(1) @binding(0) var<storage, read_write> barrier: array<u32>;
+
+@group(2) @binding(0) var<uniform> dimensions: Dimensions;
+
+fn squared_length(v: vec2<i32>) -> u32{
+    return u32(v.x * v.x) + u32(v.y * v.y);
+}
+
+//Full 64 bit product of a and b as (high, low) words
+fn wide_product(a: u32, b: u32) -> vec2<u32>{
+    let low = (a & 0xffffu) * (b & 0xffffu);
+    let middle_a = (a >> 16u) * (b & 0xffffu);
+    let middle_b = (a & 0xffffu) * (b >> 16u);
+    let carry = (low >> 16u) + (middle_a & 0xffffu) + (middle_b & 0xffffu);
+    let high = (a >> 16u) * (b >> 16u) + (middle_a >> 16u) + (middle_b >> 16u) + (carry >> 16u);
+    return vec2<u32>(high, (carry << 16u) | (low & 0xffffu));
+}
+
+fn wide_less_equal(a: vec2<u32>, b: vec2<u32>) -> bool{
+    return a.x < b.x || (a.x == b.x && a.y <= b.y);
+}
+
+//Past the ends the distance to the nearest end, in between cross(ap, ab)^2 / |ab|^2 multiplied out
+fn segment_covers(p: vec2<i32>, a: vec2<i32>, b: vec2<i32>, limit: u32) -> bool{
+    let ap = p - a;
+    let ab = b - a;
+    let along = ap.x * ab.x + ap.y * ab.y;
+    let length = squared_length(ab);
+    if(along <= 0){
+        return squared_length(ap) <= limit;
+    }
+    if(u32(along) >= length){
+        return squared_length(p - b) <= limit;
+    }
+    let cross = u32(abs(ap.x * ab.y - ap.y * ab.x));
+    return wide_less_equal(wide_product(cross, cross), wide_product(limit, length));
+}
+
+//Even-odd rule, cells within half a cell of an edge count as inside so thin slivers stay solid
+fn in_polygon(p: vec2<i32>, first: u32, count: u32, limit: u32) -> bool{
+    var inside = false;
+    var j = first + count - 1u;
+    for(var i = first; i < first + count; i++){
+        let a = vertices[i];
+        let b = vertices[j];
+        if(segment_covers(p, a, b, limit)){
+            return true;
+        }
+        //p lies left of where the edge crosses its row, compared without dividing
+        let left = (p.x - a.x) * (b.y - a.y);
+        let right = (p.y - a.y) * (b.x - a.x);
+        if((a.y > p.y) != (b.y > p.y) && select(left > right, left < right, b.y > a.y)){
+            inside = !inside;
+        }
+        j = i;
+    }
+    return inside;
+}
+
+//One primitive per z, x and y run over its bounding box
+@compute
+@workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) id: vec3<u32>){
+    let primitive = primitives[id.z];
+    if(id.x >= primitive.width || id.y >= primitive.height){
+        return;
+    }
+    let cell = vec2<u32>(primitive.x + id.x, primitive.y + id.y);
+    let p = vec2<i32>(cell) * 2;
+    let a = vec2<i32>(primitive.ax, primitive.ay);
+
+    var covered = false;
+    switch primitive.kind {
+        case 0u: {
+            covered = segment_covers(p, a, vec2<i32>(primitive.bx, primitive.by), primitive.limit);
+        }
+        case 1u: {
+            covered = squared_length(p - a) <= primitive.limit;
+        }
+        default: {
+            covered = in_polygon(p, primitive.first_vertex, primitive.vertex_count, primitive.limit);
+        }
+    }
+    if(covered){
+        barrier[cell.x + cell.y * dimensions.row] = primitive.value;
+    }
+}
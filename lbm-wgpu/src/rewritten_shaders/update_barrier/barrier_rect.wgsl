@@ -0,0 +1,30 @@
+//Rectangle of the lattice being updated, x and y are its top left cell and row is the lattice width
+struct Rect{
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    row: u32,
+}
+
+//One bit per cell of the rectangle, row by row: covered marks the cells to update and solid their new value
+@group(0) @binding(0) var<uniform> rect: Rect;
+@group(0) @binding(1) var<storage, read> covered: array<u32>;
+@group(0) @binding(2) var<storage, read> solid: array<u32>;
+
+@group(1) @binding(0) var<storage, read_write> barrier: array<u32>;
+
+@compute
+@workgroup_size(256)
+fn main(@builtin(global_invocation_id) id: vec3<u32>){
+    if(id.x >= rect.width * rect.height){
+        return;
+    }
+    let word = id.x / 32u;
+    let bit = id.x % 32u;
+    if(((covered[word] >> bit) & 1u) == 0u){
+        return;
+    }
+    let cell = (rect.y + id.x / rect.width) * rect.row + rect.x + id.x % rect.width;
+    barrier[cell] = (solid[word] >> bit) & 1u;
+}
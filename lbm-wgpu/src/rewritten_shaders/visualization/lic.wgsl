@@ -0,0 +1,99 @@
+//Modulate 0 replaces the colors with the convolution, 1 shades the color map's colors with it
+struct Params{
+    row: u32,
+    col: u32,
+    modulate: u32,
+}
+
+@group(0) @binding(0) var<storage, read_write> colors: array<vec3<f32>>;
+
+@group(1) @binding(0) var<storage, read_write> ux: array<f32>;
+@group(1) @binding(1) var<storage, read_write> uy: array<f32>;
+@group(1) @binding(2) var<storage, read_write> rho: array<f32>;
+
+@group(2) @binding(0) var<storage, read_write> barrier: array<u32>;
+
+@group(3) @binding(0) var<uniform> params: Params;
+
+//Steps of STEP_LENGTH cells traced each way along the streamline
+const STEPS: i32 = 20;
+const STEP_LENGTH: f32 = 0.5;
+//Averaging 41 samples of black and white noise leaves little contrast, this stretches it back out
+const CONTRAST: f32 = 3.0;
+
+fn index(cell: vec2<i32>) -> u32{
+    return u32(cell.x) + u32(cell.y) * params.row;
+}
+
+fn inside(cell: vec2<i32>) -> bool{
+    return cell.x >= 0 && cell.y >= 0 && cell.x < i32(params.row) && cell.y < i32(params.col);
+}
+
+//Black or white, fixed to the cell so the texture holds still between frames
+fn noise(cell: vec2<i32>) -> f32{
+    var h = index(cell) * 747796405u + 2891336453u;
+    h = ((h >> ((h >> 28u) + 4u)) ^ h) * 277803737u;
+    h = (h >> 22u) ^ h;
+    return f32(h & 1u);
+}
+
+//Flow direction at a point in cells, bilinear between cell centers. Momentum is used as is since only its
+//direction matters, y is flipped because north is up the lattice, towards lower rows
+fn direction(point: vec2<f32>) -> vec2<f32>{
+    let corner = point - 0.5;
+    let base = vec2<i32>(floor(corner));
+    let weight = corner - floor(corner);
+    var sum = vec2<f32>(0.0, 0.0);
+    for (var dy = 0; dy < 2; dy++) {
+        for (var dx = 0; dx < 2; dx++) {
+            let cell = clamp(base + vec2<i32>(dx, dy), vec2<i32>(0, 0), vec2<i32>(i32(params.row) - 1, i32(params.col) - 1));
+            let w = select(1.0 - weight.x, weight.x, dx == 1) * select(1.0 - weight.y, weight.y, dy == 1);
+            sum += w * vec2<f32>(ux[index(cell)], -uy[index(cell)]);
+        }
+    }
+    return sum;
+}
+
+//Noise summed along the streamline from start and the number of samples, stopping at barriers and edges
+fn trace(start: vec2<f32>, sign: f32) -> vec2<f32>{
+    var point = start;
+    var total = vec2<f32>(0.0, 0.0);
+    for (var step = 0; step < STEPS; step++) {
+        let d = direction(point);
+        let magnitude = length(d);
+        if (magnitude < 1e-9) {
+            break;
+        }
+        point += sign * STEP_LENGTH * d/magnitude;
+        let cell = vec2<i32>(floor(point));
+        if (!inside(cell) || barrier[index(cell)] == 1u) {
+            break;
+        }
+        total += vec2<f32>(noise(cell), 1.0);
+    }
+    return total;
+}
+
+@compute
+@workgroup_size(256)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>){
+    let i = global_invocation_id.x;
+    if (i >= params.row * params.col) {
+        return;
+    }
+    if (barrier[i] == 1u) {
+        colors[i] = vec3(0.0, 0.0, 0.0);
+        return;
+    }
+
+    let cell = vec2<i32>(i32(i % params.row), i32(i / params.row));
+    let start = vec2<f32>(cell) + 0.5;
+    let total = vec2<f32>(noise(cell), 1.0) + trace(start, 1.0) + trace(start, -1.0);
+    let intensity = clamp((total.x/total.y - 0.5) * CONTRAST + 0.5, 0.0, 1.0);
+
+    if (params.modulate == 1u) {
+        colors[i] = min(colors[i] * (0.4 + 0.8 * intensity), vec3(1.0, 1.0, 1.0));
+    } else {
+        colors[i] = vec3(intensity, intensity, intensity);
+    }
+}
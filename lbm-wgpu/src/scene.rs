@@ -0,0 +1,437 @@
+use serde::{Serialize, Deserialize};
+
+use crate::barrier_shapes::{Shape, blob::Blob, mask::Mask, line::Line, brush::{Brush, BrushShape}, circle::Circle, ellipse::Ellipse, rectangle::Rectangle, polygon::Polygon, airfoil::Airfoil, primitive::Primitive, text, transform::Affine, merge_shapes};
+
+//Text spanning 3/5 of the width, centered on a 16:9 lattice
+pub const WELCOME: &str = include_str!("scenes/welcome.json");
+pub const CURL: &str = include_str!("scenes/curl.json");
+pub const CHAOS: &str = include_str!("scenes/chaos.json");
+pub const SWITCHING_LINES: &str = include_str!("scenes/switching_lines.json");
+
+//Cells shapes authored on lattices more than this many times larger in either direction are rejected
+//rather than allocated
+const MAX_CELLS_SCALE: u32 = 4;
+
+//A lattice setup stored as JSON, positions are fractions of the lattice width and height so a scene
+//can be loaded at any resolution
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct Scene{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lattice: Option<LatticeSize>,
+    pub fluid: FluidSettings,
+    pub boundaries: Boundaries,
+    pub shapes: Vec<SceneShape>,
+}
+
+//Size the scene was authored on, the lattice itself is fixed by the resolution chosen at startup
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+pub struct LatticeSize{
+    pub width: u32,
+    pub height: u32,
+}
+
+//Anything left out keeps its current value when the scene is loaded
+#[derive(Serialize, Deserialize, Default, PartialEq, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct FluidSettings{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub viscosity: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compute_per_render: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct Boundaries{
+    pub top_wall: bool,
+    pub bottom_wall: bool,
+}
+
+impl Default for Boundaries{
+    fn default() -> Self{
+        Boundaries{
+            top_wall: true,
+            bottom_wall: true,
+        }
+    }
+}
+
+impl Boundaries{
+    //Top and bottom rows, drawn where a wall is wanted and erased where it isn't
+    pub fn walls(&self, x: u32, y: u32) -> Blob{
+        let mut mask = Mask::new_empty();
+        mask.reserve((0, 0), (x as isize - 1, y as isize - 1));
+        for i in 0..x as isize{
+            mask.set((i, 0), self.top_wall);
+            mask.set((i, y as isize - 1), self.bottom_wall);
+        }
+        Blob::new(mask)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SceneShape{
+    #[serde(flatten)]
+    pub kind: ShapeKind,
+    //Erases the shape's cells instead of drawing them
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub erase: bool,
+    //Anything but union replaces the shape before it with the two combined, so a channel can be carved out
+    //of a solid by following it with a shape using difference
+    #[serde(default, skip_serializing_if = "SetOperation::is_union")]
+    pub op: SetOperation,
+}
+
+#[derive(Serialize, Deserialize, Default, PartialEq, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SetOperation{
+    #[default]
+    Union,
+    Intersection,
+    Difference,
+    SymmetricDifference,
+}
+
+//Lengths are fractions of the lattice width unless they come in (width, height) pairs
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ShapeKind{
+    //Width is the round pen's width in cells
+    Line{
+        start: (f64, f64),
+        end: (f64, f64),
+        #[serde(default = "default_line_width")]
+        width: f64,
+    },
+    Circle{
+        center: (f64, f64),
+        radius: f64,
+    },
+    Ellipse{
+        center: (f64, f64),
+        radii: (f64, f64),
+        #[serde(default)]
+        rotation: f64,
+    },
+    Rectangle{
+        center: (f64, f64),
+        size: (f64, f64),
+        #[serde(default)]
+        rotation: f64,
+    },
+    Polygon{
+        vertices: Vec<(f64, f64)>,
+    },
+    Naca{
+        code: String,
+        chord: f64,
+        leading_edge: (f64, f64),
+        #[serde(default)]
+        angle_of_attack: f64,
+    },
+    //Position is the top left corner of the first line, size is the cap height and thickness is the pen width in cells
+    Text{
+        text: String,
+        size: f64,
+        position: (f64, f64),
+        #[serde(default)]
+        rotation: f64,
+        #[serde(default)]
+        thickness: f64,
+    },
+    //Exact cells as [row, first column, length] runs on a width x height lattice, rescaled if the lattice differs
+    Cells{
+        width: u32,
+        height: u32,
+        #[serde(default)]
+        solid: Vec<[u32; 3]>,
+        #[serde(default)]
+        erased: Vec<[u32; 3]>,
+    },
+}
+
+impl Scene{
+
+    pub fn from_json(source: &str) -> Result<Scene, String>{
+        serde_json::from_str(source).map_err(|e| format!("Invalid scene: {}", e))
+    }
+
+    pub fn to_json(&self) -> Result<String, String>{
+        serde_json::to_string(self).map_err(|e| e.to_string())
+    }
+
+    //Stores the whole barrier as exact cells so freehand drawing survives the round trip, including shapes
+    //that have dropped off the undo history
+    pub fn from_barrier(barrier: &dyn Shape, fluid: FluidSettings, boundaries: Boundaries, x: u32, y: u32) -> Scene{
+        Scene{
+            lattice: Some(LatticeSize{ width: x, height: y }),
+            fluid,
+            boundaries,
+            shapes: Some(barrier).filter(|s| !s.is_empty())
+                .map(|s| SceneShape{ kind: ShapeKind::from_shape(s, x, y), erase: false, op: SetOperation::Union })
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    pub fn build_shapes(&self, x: u32, y: u32) -> Result<Vec<Box<dyn Shape>>, String>{
+        self.groups().map(|g| SceneShape::build_group(g, x, y)).collect()
+    }
+
+    //Each shape along with the shapes combined into it, every group builds to a single shape
+    pub fn groups(&self) -> impl Iterator<Item = &[SceneShape]>{
+        self.shapes.chunk_by(|_, next| next.op != SetOperation::Union)
+    }
+
+    //Boundaries and shapes merged in order, for presets that are drawn without going through the undo history
+    pub fn build_barrier(&self, x: u32, y: u32) -> Result<Blob, String>{
+        let mut blob = self.boundaries.walls(x, y);
+        for shape in self.build_shapes(x, y)?{
+            blob.join(&*shape);
+        }
+        Ok(blob)
+    }
+}
+
+impl SceneShape{
+    pub fn build(&self, x: u32, y: u32) -> Result<Box<dyn Shape>, String>{
+        let shape = self.kind.build(x, y)?;
+        Ok(if self.erase { Box::new(shape.negate()) } else { shape })
+    }
+
+    //The first shape with the rest combined into it in order, their own erase flags applied first
+    pub fn build_group(group: &[SceneShape], x: u32, y: u32) -> Result<Box<dyn Shape>, String>{
+        let (first, rest) = group.split_first().ok_or("Empty shape group")?;
+        let mut shape = first.build(x, y)?;
+        for next in rest{
+            shape = Box::new(next.op.apply(&*shape, &*next.build(x, y)?));
+        }
+        Ok(shape)
+    }
+}
+
+impl SetOperation{
+    pub fn apply(&self, s1: &dyn Shape, s2: &dyn Shape) -> Blob{
+        match self {
+            SetOperation::Union => merge_shapes::merge(s1, s2),
+            SetOperation::Intersection => merge_shapes::intersection(s1, s2),
+            SetOperation::Difference => merge_shapes::difference(s1, s2),
+            SetOperation::SymmetricDifference => merge_shapes::symmetric_difference(s1, s2),
+        }
+    }
+
+    fn is_union(&self) -> bool{
+        *self == SetOperation::Union
+    }
+}
+
+impl ShapeKind{
+
+    fn build(&self, x: u32, y: u32) -> Result<Box<dyn Shape>, String>{
+        let (xdim, ydim) = (x as isize, y as isize);
+        let position = |p: &(f64, f64)| (p.0 * x as f64, p.1 * y as f64);
+        let cell = |p: &(f64, f64)| {
+            let p = position(p);
+            ((p.0.round() as isize).clamp(0, xdim - 1), (p.1.round() as isize).clamp(0, ydim - 1))
+        };
+
+        Ok(match self {
+            ShapeKind::Line { start, end, width } =>
+                Box::new(Line::with_brush(cell(start), cell(end), &line_brush(*width, xdim, ydim), true, xdim, ydim)),
+            ShapeKind::Circle { center, radius } => Box::new(Circle::new(cell(center), radius * x as f64, xdim, ydim)),
+            ShapeKind::Ellipse { center, radii, rotation } =>
+                Box::new(Ellipse::new(position(center), position(radii), *rotation, xdim, ydim)),
+            ShapeKind::Rectangle { center, size, rotation } =>
+                Box::new(Rectangle::rotated(position(center), position(size), *rotation, xdim, ydim)),
+            ShapeKind::Polygon { vertices } => {
+                if vertices.len() < 3{
+                    return Err("Polygons need at least 3 vertices".to_string());
+                }
+                let vertices: Vec<(f64, f64)> = vertices.iter().map(position).collect();
+                Box::new(Polygon::new(&vertices, xdim, ydim))
+            },
+            ShapeKind::Naca { code, chord, leading_edge, angle_of_attack } =>
+                Box::new(Airfoil::naca(code, chord * x as f64, position(leading_edge), *angle_of_attack, xdim, ydim)?),
+            ShapeKind::Text { text, size, position: corner, rotation, thickness } =>
+                Box::new(text::render(text, size * x as f64, position(corner), *rotation, *thickness, xdim, ydim)),
+            ShapeKind::Cells { width, height, solid, erased } => {
+                if *width == 0 || *height == 0 || *width > MAX_CELLS_SCALE * x || *height > MAX_CELLS_SCALE * y{
+                    return Err(format!("Cells on a {} x {} lattice can't be loaded onto {} x {}", width, height, x, y));
+                }
+                let mut mask = Mask::new_empty();
+                mask.reserve((0, 0), (*width as isize - 1, *height as isize - 1));
+                for (runs, value) in [(solid, true), (erased, false)]{
+                    for run in runs.iter(){
+                        let end = run[1].checked_add(run[2]).filter(|end| *end <= *width && run[0] < *height)
+                            .ok_or_else(|| format!("Cell run {:?} lies outside the {} x {} lattice", run, width, height))?;
+                        for c in run[1]..end{
+                            mask.set((c as isize, run[0] as isize), value);
+                        }
+                    }
+                }
+                let blob = Blob::new(mask);
+                if (*width, *height) == (x, y){
+                    Box::new(blob)
+                } else {
+                    //Cell centers sit on integers, so scaling the cell edges means pivoting on (-0.5, -0.5)
+                    let factor = (x as f64 / (*width).max(1) as f64, y as f64 / (*height).max(1) as f64);
+                    Box::new(blob.transform(&Affine::scale(factor, (-0.5, -0.5)), xdim, ydim))
+                }
+            },
+        })
+    }
+
+    //The shape as something the GPU can rasterize directly, None for shapes that need the CPU
+    pub fn primitive(&self, x: u32, y: u32) -> Option<Primitive>{
+        let position = |p: &(f64, f64)| (p.0 * x as f64, p.1 * y as f64);
+        let cell = |p: &(f64, f64)| {
+            let p = position(p);
+            (p.0.round().clamp(0.0, x as f64 - 1.0), p.1.round().clamp(0.0, y as f64 - 1.0))
+        };
+        match self {
+            ShapeKind::Line { start, end, width } => {
+                let (start, end) = (cell(start), cell(end));
+                let brush = line_brush(*width, x as isize, y as isize);
+                Some(brush.segment((start.0 as isize, start.1 as isize), (end.0 as isize, end.1 as isize)))
+            },
+            ShapeKind::Circle { center, radius } => Some(Primitive::Circle{ center: cell(center), radius: radius * x as f64 }),
+            ShapeKind::Polygon { vertices } if vertices.len() >= 3 =>
+                Some(Primitive::Polygon{ vertices: vertices.iter().map(position).collect() }),
+            ShapeKind::Rectangle { center, size, rotation } => {
+                let (center, (hw, hh)) = (position(center), position(&(size.0/2.0, size.1/2.0)));
+                let (sin, cos) = rotation.to_radians().sin_cos();
+                let vertices = [(-hw, -hh), (hw, -hh), (hw, hh), (-hw, hh)].iter()
+                    .map(|c| (center.0 + c.0 * cos - c.1 * sin, center.1 + c.0 * sin + c.1 * cos))
+                    .collect();
+                Some(Primitive::Polygon{ vertices })
+            },
+            _ => None,
+        }
+    }
+
+    fn from_shape(shape: &dyn Shape, x: u32, y: u32) -> ShapeKind{
+        let mut solid = Vec::<(u32, u32)>::new();
+        let mut erased = Vec::<(u32, u32)>::new();
+        for p in shape.get_mask().points().filter(|p| p.0 >= 0 && p.1 >= 0){
+            let cell = (p.1 as u32, p.0 as u32);
+            if p.2 { solid.push(cell) } else { erased.push(cell) }
+        }
+        ShapeKind::Cells{
+            width: x,
+            height: y,
+            solid: Self::runs(solid),
+            erased: Self::runs(erased),
+        }
+    }
+
+    fn runs(mut cells: Vec<(u32, u32)>) -> Vec<[u32; 3]>{
+        cells.sort_unstable();
+        let mut runs = Vec::<[u32; 3]>::new();
+        for (row, column) in cells{
+            match runs.last_mut() {
+                Some(run) if run[0] == row && run[1] + run[2] == column => run[2] += 1,
+                _ => runs.push([row, column, 1]),
+            }
+        }
+        runs
+    }
+}
+
+fn line_brush(width: f64, xdim: isize, ydim: isize) -> Brush{
+    Brush::new(width, BrushShape::Round).fit(xdim, ydim)
+}
+
+fn default_line_width() -> f64{
+    Brush::default().width
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn solid(shape: &dyn Shape) -> Vec<(isize, isize)>{
+        shape.get_mask().points().filter(|p| p.2).map(|p| (p.0, p.1)).collect()
+    }
+
+    fn cells(width: u32, height: u32, solid: Vec<[u32; 3]>) -> String{
+        serde_json::to_string(&Scene{
+            shapes: vec![SceneShape{ kind: ShapeKind::Cells{ width, height, solid, erased: vec![] }, erase: false, op: SetOperation::Union }],
+            ..Scene::default()
+        }).unwrap()
+    }
+
+    #[test]
+    fn presets_build(){
+        for preset in [WELCOME, CURL, CHAOS, SWITCHING_LINES]{
+            let barrier = Scene::from_json(preset).unwrap().build_barrier(160, 90).unwrap();
+            assert!(barrier.get_mask().len() > 2 * 160);
+        }
+    }
+
+    #[test]
+    fn exported_barrier_round_trips(){
+        let scene = Scene::from_json(CHAOS).unwrap();
+        let barrier = scene.build_barrier(64, 48).unwrap();
+        let fluid = FluidSettings{ viscosity: Some(0.05), speed: None, compute_per_render: Some(10) };
+        let json = Scene::from_barrier(&barrier, fluid, Boundaries::default(), 64, 48).to_json().unwrap();
+
+        let loaded = Scene::from_json(&json).unwrap();
+        assert_eq!(loaded.fluid, fluid);
+        assert_eq!(loaded.lattice, Some(LatticeSize{ width: 64, height: 48 }));
+        assert_eq!(loaded.shapes.len(), 1);
+        assert_eq!(solid(&loaded.build_barrier(64, 48).unwrap()), solid(&barrier));
+    }
+
+    #[test]
+    fn cells_are_validated(){
+        let build = |source: String| Scene::from_json(&source).unwrap().build_shapes(10, 10);
+        assert!(build(cells(10, 10, vec![[2, 3, 4]])).is_ok());
+        assert!(build(cells(0, 10, vec![])).is_err());
+        assert!(build(cells(10 * MAX_CELLS_SCALE + 1, 10, vec![])).is_err());
+        assert!(build(cells(10, 10, vec![[2, 8, 3]])).is_err());
+        assert!(build(cells(10, 10, vec![[10, 0, 1]])).is_err());
+        assert!(build(cells(10, 10, vec![[0, 1, u32::MAX]])).is_err());
+    }
+
+    #[test]
+    fn cells_are_rescaled(){
+        let shapes = Scene::from_json(&cells(5, 5, vec![[1, 1, 2]])).unwrap().build_shapes(10, 10).unwrap();
+        assert_eq!(solid(&*shapes[0]), vec![(2, 2), (3, 2), (4, 2), (5, 2), (2, 3), (3, 3), (4, 3), (5, 3)]);
+    }
+
+    #[test]
+    fn set_operations_combine_with_the_shape_before(){
+        let source = r#"{"shapes": [
+            {"type": "rectangle", "center": [0.5, 0.5], "size": [0.6, 0.6]},
+            {"type": "circle", "center": [0.5, 0.5], "radius": 0.1, "op": "difference"},
+            {"type": "circle", "center": [0.1, 0.1], "radius": 0.05}
+        ]}"#;
+        let scene = Scene::from_json(source).unwrap();
+        assert_eq!(scene.groups().map(|g| g.len()).collect::<Vec<_>>(), vec![2, 1]);
+        let shapes = scene.build_shapes(40, 40).unwrap();
+        assert_eq!(shapes[0].get_mask().get((20, 20)), Some(false));
+        assert_eq!(shapes[0].get_mask().get((14, 14)), Some(true));
+    }
+
+    #[test]
+    fn lines_default_to_the_draw_brush_width(){
+        let scene = Scene::from_json(r#"{"shapes": [{"type": "line", "start": [0.1, 0.5], "end": [0.9, 0.5]}]}"#).unwrap();
+        match scene.shapes[0].kind {
+            ShapeKind::Line{ width, .. } => assert_eq!(width, Brush::default().width),
+            _ => panic!("Expected a line"),
+        }
+        let shapes = scene.build_shapes(20, 20).unwrap();
+        assert_eq!(shapes[0].get_mask().rectangle(), ((1, 9), 19, 3));
+    }
+
+    #[test]
+    fn invalid_scenes_are_errors(){
+        assert!(Scene::from_json("{").is_err());
+        assert!(Scene::from_json(r#"{"shapes": [{"type": "hexagon"}]}"#).is_err());
+        let polygon = r#"{"shapes": [{"type": "polygon", "vertices": [[0.1, 0.1], [0.2, 0.2]]}]}"#;
+        assert!(Scene::from_json(polygon).unwrap().build_shapes(10, 10).is_err());
+    }
+}